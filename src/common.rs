@@ -1,6 +1,8 @@
+use std::fmt;
 use std::str::FromStr;
 use roxmltree::Node;
-use crate::{Error, Result};
+use serde_json::{json, Value};
+use crate::{json_i32, json_object, Error, Result};
 
 /// Orientation of the map.
 /// Either Orthogonal, Isometric, Staggered or Hexagonal.
@@ -14,7 +16,24 @@ pub enum Orientation {
 }
 
 impl Orientation {
-    pub(crate) fn parse(value: &str) -> Result<Self> {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    /// Name of the orientation as written in Tiled files.
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Orthogonal => "orthogonal",
+            Self::Isometric => "isometric",
+            Self::Staggered => "staggered",
+            Self::Hexagonal => "hexagonal",
+        }
+    }
+}
+
+impl FromStr for Orientation {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
         match value {
             "orthogonal" => Ok(Self::Orthogonal),
             "isometric" => Ok(Self::Isometric),
@@ -40,6 +59,22 @@ impl TileOffset {
         }
         Ok(result)
     }
+
+    pub(crate) fn parse_json(value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(value)? {
+            match name.as_str() {
+                "x" => result.x = json_i32(value)?,
+                "y" => result.y = json_i32(value)?,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn to_json(self) -> Value {
+        json!({ "x": self.x, "y": self.y })
+    }
 }
 
 /// An RGBA color.
@@ -62,6 +97,24 @@ impl Color {
     pub const BLACK: Color = Color { r: 0, g: 0, b: 0, a: 255 };
     pub const TRANSPARENT: Color = Color { r: 0, g: 0, b: 0, a: 0 };
 
+    /// Component-wise product of two colors, treating components as values between 0 and 1.
+    pub fn multiply(self, other: Color) -> Color {
+        fn mul(a: u8, b: u8) -> u8 {
+            ((a as u32 * b as u32 + 127) / 255) as u8
+        }
+        Self {
+            r: mul(self.r, other.r),
+            g: mul(self.g, other.g),
+            b: mul(self.b, other.b),
+            a: mul(self.a, other.a),
+        }
+    }
+
+    /// Same color with a different alpha.
+    pub fn with_alpha(self, a: u8) -> Color {
+        Self { a, ..self }
+    }
+
     fn from_argb(value: u32) -> Self {
         let a = (value >> 24) & 0xFF;
         let r = (value >> 16) & 0xFF;
@@ -91,9 +144,7 @@ impl Color {
 impl FromStr for Color {
     type Err = Error;
     fn from_str(s: &str) -> Result<Self> {
-        let s =
-            if s.starts_with('#') { &s[1..] }
-            else { s };
+        let s = s.strip_prefix('#').unwrap_or(s);
         match s.len() {
             6 => {
                 let rgb = u32::from_str_radix(s, 16).map_err(|_| Error::ParsingError)?;
@@ -103,7 +154,75 @@ impl FromStr for Color {
                 let argb = u32::from_str_radix(s, 16).map_err(|_| Error::ParsingError)?;
                 Ok(Self::from_argb(argb))
             },
-            _ => return Err(Error::ParsingError),
+            _ => Err(Error::ParsingError),
+        }
+    }
+}
+
+/// Formats the color as Tiled writes it: `#RRGGBB` when opaque, and `#AARRGGBB` otherwise.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.a {
+            255 => write!(f, "#{:02x}{:02x}{:02x}", self.r, self.g, self.b),
+            a => write!(f, "#{:02x}{:02x}{:02x}{:02x}", a, self.r, self.g, self.b),
         }
     }
-}
\ No newline at end of file
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Color, DrawOrder, Error, FillMode, HAlign, ObjectAlignment, Orientation, RenderOrder, StaggerAxis, StaggerIndex, TileRenderSize, VAlign, WangSetType};
+
+    #[test]
+    fn test_multiply() {
+        let color = Color { r: 12, g: 128, b: 200, a: 255 };
+        assert_eq!(color, Color::WHITE.multiply(color));
+        assert_eq!(color, color.multiply(Color::WHITE));
+        assert_eq!(Color::BLACK, Color::BLACK.multiply(color));
+        assert_eq!(Color { r: 6, g: 64, b: 100, a: 255 }, color.multiply(Color { r: 128, g: 128, b: 128, a: 255 }));
+    }
+
+    #[test]
+    fn test_with_alpha() {
+        let color = Color::WHITE.with_alpha(64);
+        assert_eq!(Color { r: 255, g: 255, b: 255, a: 64 }, color);
+        assert_eq!(Color::TRANSPARENT, Color::BLACK.with_alpha(0));
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!("#00eaff", Color { r: 0, g: 234, b: 255, a: 255 }.to_string());
+        assert_eq!("#80ff0000", Color { r: 255, g: 0, b: 0, a: 128 }.to_string());
+        let color: Color = "#80ff0000".parse().unwrap();
+        assert_eq!(color, color.to_string().parse().unwrap());
+    }
+
+    #[test]
+    fn test_enums_from_str() {
+        assert_eq!(Orientation::Isometric, "isometric".parse::<Orientation>().unwrap());
+        assert_eq!(RenderOrder::LeftUp, "left-up".parse::<RenderOrder>().unwrap());
+        assert_eq!(StaggerAxis::X, "x".parse::<StaggerAxis>().unwrap());
+        assert_eq!(StaggerIndex::Even, "even".parse::<StaggerIndex>().unwrap());
+        assert_eq!(DrawOrder::TopDown, "topdown".parse::<DrawOrder>().unwrap());
+        assert_eq!(FillMode::PreserveAspectFit, "preserve-aspect-fit".parse::<FillMode>().unwrap());
+        assert_eq!(TileRenderSize::Grid, "grid".parse::<TileRenderSize>().unwrap());
+        assert_eq!(ObjectAlignment::BottomLeft, "bottomleft".parse::<ObjectAlignment>().unwrap());
+        assert_eq!(HAlign::Justify, "justify".parse::<HAlign>().unwrap());
+        assert_eq!(VAlign::Bottom, "bottom".parse::<VAlign>().unwrap());
+        assert_eq!(WangSetType::Mixed, "mixed".parse::<WangSetType>().unwrap());
+        assert_eq!(Orientation::Hexagonal, Orientation::parse("hexagonal").unwrap());
+        assert_eq!(RenderOrder::RightUp, RenderOrder::parse("right-up").unwrap());
+        assert_eq!(StaggerAxis::Y, StaggerAxis::parse("y").unwrap());
+        assert_eq!(StaggerIndex::Odd, StaggerIndex::parse("odd").unwrap());
+        assert_eq!(DrawOrder::Index, DrawOrder::parse("index").unwrap());
+        assert_eq!(FillMode::Stretch, FillMode::parse("stretch").unwrap());
+        assert_eq!(TileRenderSize::Tile, TileRenderSize::parse("tile").unwrap());
+        assert_eq!(ObjectAlignment::Center, ObjectAlignment::parse("center").unwrap());
+        assert_eq!(HAlign::Center, HAlign::parse("center").unwrap());
+        assert_eq!(VAlign::Top, VAlign::parse("top").unwrap());
+        assert_eq!(WangSetType::Edge, WangSetType::parse("edge").unwrap());
+        assert!(matches!(StaggerAxis::parse("z"), Err(Error::ParsingError)));
+        assert!(matches!("Isometric".parse::<Orientation>(), Err(Error::ParsingError)));
+    }
+}
@@ -0,0 +1,214 @@
+use crate::{Map, Orientation, StaggerAxis, StaggerIndex};
+
+
+/// Converts between tile and pixel coordinates of a [`Map`], whatever its orientation.
+/// Pixel coordinates of a tile are those of the top-left corner of its bounding box.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct CoordinateSystem {
+    orientation: Orientation,
+    tile_width: u32,
+    tile_height: u32,
+    map_height: u32,
+    hex_side_length: u32,
+    stagger_axis: StaggerAxis,
+    stagger_index: StaggerIndex,
+}
+
+impl CoordinateSystem {
+    pub fn orientation(&self) -> Orientation { self.orientation }
+
+    pub(crate) fn new(map: &Map) -> Self {
+        Self {
+            orientation: map.orientation(),
+            tile_width: map.tile_width(),
+            tile_height: map.tile_height(),
+            map_height: map.height(),
+            hex_side_length: map.hex_side_length().unwrap_or(0).max(0) as u32,
+            stagger_axis: map.stagger_axis().unwrap_or_default(),
+            stagger_index: map.stagger_index().unwrap_or_default(),
+        }
+    }
+
+    /// Pixel coordinates of a tile.
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        match self.orientation {
+            Orientation::Orthogonal => (x as f32 * tile_width, y as f32 * tile_height),
+            Orientation::Isometric => (
+                (x - y + self.map_height as i32 - 1) as f32 * tile_width / 2.0,
+                (x + y) as f32 * tile_height / 2.0,
+            ),
+            Orientation::Staggered | Orientation::Hexagonal => self.staggered_tile_to_pixel(x, y),
+        }
+    }
+
+    /// Coordinates of the tile containing a pixel.
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        match self.orientation {
+            Orientation::Orthogonal => ((x / tile_width).floor() as i32, (y / tile_height).floor() as i32),
+            Orientation::Isometric => {
+                let x = x / tile_width - self.map_height as f32 / 2.0;
+                let y = y / tile_height;
+                ((y + x).floor() as i32, (y - x).floor() as i32)
+            },
+            Orientation::Staggered | Orientation::Hexagonal => self.staggered_pixel_to_tile(x, y),
+        }
+    }
+
+    /// Lengths of the flat sides of hexagons along the x and y axes.
+    /// Zero for staggered maps, whose tiles are diamonds.
+    fn side_lengths(&self) -> (f32, f32) {
+        let side_length = match self.orientation {
+            Orientation::Hexagonal => self.hex_side_length as f32,
+            _ => 0.0,
+        };
+        match self.stagger_axis {
+            StaggerAxis::X => (side_length, 0.0),
+            StaggerAxis::Y => (0.0, side_length),
+        }
+    }
+
+    /// Distances between neighbouring columns and rows.
+    fn column_width_and_row_height(&self) -> (f32, f32) {
+        let (side_x, side_y) = self.side_lengths();
+        let column_width = (self.tile_width as f32 - side_x) / 2.0 + side_x;
+        let row_height = (self.tile_height as f32 - side_y) / 2.0 + side_y;
+        (column_width, row_height)
+    }
+
+    /// True if the column or row at an index along the stagger axis is shifted.
+    fn is_shifted(&self, index: i32) -> bool {
+        let odd = index.rem_euclid(2) == 1;
+        match self.stagger_index {
+            StaggerIndex::Odd => odd,
+            StaggerIndex::Even => !odd,
+        }
+    }
+
+    fn staggered_tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let (side_x, side_y) = self.side_lengths();
+        let (column_width, row_height) = self.column_width_and_row_height();
+        match self.stagger_axis {
+            StaggerAxis::X => {
+                let mut pixel_y = y as f32 * (self.tile_height as f32 + side_y);
+                if self.is_shifted(x) {
+                    pixel_y += row_height;
+                }
+                (x as f32 * column_width, pixel_y)
+            },
+            StaggerAxis::Y => {
+                let mut pixel_x = x as f32 * (self.tile_width as f32 + side_x);
+                if self.is_shifted(y) {
+                    pixel_x += column_width;
+                }
+                (pixel_x, y as f32 * row_height)
+            },
+        }
+    }
+
+    fn staggered_pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        let (column_width, row_height) = self.column_width_and_row_height();
+        let (approx_x, approx_y) = match self.stagger_axis {
+            StaggerAxis::X => ((x / column_width).floor() as i32, (y / tile_height).floor() as i32),
+            StaggerAxis::Y => ((x / tile_width).floor() as i32, (y / row_height).floor() as i32),
+        };
+
+        // Tiles overlap their neighbours' bounding boxes, so the closest of the neighbouring tiles is picked.
+        // Diamonds are measured with the distance matching their shape, and hexagons by their centers.
+        let mut nearest = (approx_x, approx_y);
+        let mut nearest_distance = f32::MAX;
+        for tile_y in approx_y - 1 ..= approx_y + 1 {
+            for tile_x in approx_x - 1 ..= approx_x + 1 {
+                let (pixel_x, pixel_y) = self.staggered_tile_to_pixel(tile_x, tile_y);
+                let dx = (x - pixel_x - tile_width / 2.0).abs();
+                let dy = (y - pixel_y - tile_height / 2.0).abs();
+                let distance = match self.orientation {
+                    Orientation::Hexagonal => dx * dx + dy * dy,
+                    _ => dx / tile_width + dy / tile_height,
+                };
+                if distance < nearest_distance {
+                    nearest = (tile_x, tile_y);
+                    nearest_distance = distance;
+                }
+            }
+        }
+        nearest
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Map;
+
+    /// Checks that the center of every tile in the map converts back to the tile.
+    fn assert_round_trip(map: &Map) {
+        let coordinates = map.coordinate_system();
+        let half_width = map.tile_width() as f32 / 2.0;
+        let half_height = map.tile_height() as f32 / 2.0;
+        for y in -2..map.height() as i32 + 2 {
+            for x in -2..map.width() as i32 + 2 {
+                let (pixel_x, pixel_y) = coordinates.tile_to_pixel(x, y);
+                let tile = coordinates.pixel_to_tile(pixel_x + half_width, pixel_y + half_height);
+                assert_eq!((x, y), tile, "{:?}", coordinates.orientation());
+            }
+        }
+    }
+
+    #[test]
+    fn test_orthogonal() {
+        let map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        let coordinates = map.coordinate_system();
+        assert_eq!((40.0, 60.0), coordinates.tile_to_pixel(2, 3));
+        assert_eq!((-1, 0), coordinates.pixel_to_tile(-0.5, 19.9));
+        assert_round_trip(&map);
+    }
+
+    #[test]
+    fn test_isometric() {
+        let map = Map::parse_str(include_str!("test_data/isometric.tmx")).unwrap();
+        let coordinates = map.coordinate_system();
+        assert_eq!((30.0, 0.0), coordinates.tile_to_pixel(0, 0));
+        assert_eq!((40.0, 10.0), coordinates.tile_to_pixel(1, 0));
+        assert_eq!((20.0, 10.0), coordinates.tile_to_pixel(0, 1));
+        assert_round_trip(&map);
+    }
+
+    #[test]
+    fn test_staggered() {
+        let map = Map::parse_str(include_str!("test_data/isometric_staggered.tmx")).unwrap();
+        let coordinates = map.coordinate_system();
+        assert_eq!((10.0, 0.0), coordinates.tile_to_pixel(0, 0));
+        assert_eq!((0.0, 10.0), coordinates.tile_to_pixel(0, 1));
+        assert_eq!((30.0, 20.0), coordinates.tile_to_pixel(1, 2));
+
+        // Corners of a tile's bounding box belong to its neighbours.
+        assert_eq!((0, 0), coordinates.pixel_to_tile(20.0, 10.0));
+        assert_eq!((0, 1), coordinates.pixel_to_tile(11.0, 19.0));
+        assert_round_trip(&map);
+    }
+
+    #[test]
+    fn test_hexagonal() {
+        let map = Map::parse_str(include_str!("test_data/hexagonal.tmx")).unwrap();
+        let coordinates = map.coordinate_system();
+        assert_eq!((0.0, 0.0), coordinates.tile_to_pixel(0, 0));
+        assert_eq!((20.0, 0.0), coordinates.tile_to_pixel(1, 0));
+        assert_eq!((10.0, 15.0), coordinates.tile_to_pixel(0, 1));
+        assert_eq!((0, 1), coordinates.pixel_to_tile(19.0, 19.0));
+        assert_round_trip(&map);
+    }
+
+    #[test]
+    fn test_stagger_x() {
+        for xml in [include_str!("test_data/isometric_staggered.tmx"), include_str!("test_data/hexagonal.tmx")] {
+            let xml = xml.replace(r#"staggeraxis="y""#, r#"staggeraxis="x""#);
+            let map = Map::parse_str(&xml).unwrap();
+            assert_round_trip(&map);
+        }
+    }
+}
@@ -1,5 +1,4 @@
 use std::num::{ParseFloatError, ParseIntError};
-use std::str::ParseBoolError;
 use thiserror::Error;
 
 /// Any error that can occur during parsing.
@@ -13,40 +12,64 @@ pub enum Error {
     InvalidLayerError,
     #[error("Failed to decode tile layers")]
     DecodeLayerError,
-    #[error("XML parsing failed")]
-    XmlParsingError,
+    /// The JSON is well-formed, but a value is missing or has an unexpected type.
     #[error("JSON parsing failed")]
     JsonParsingError,
+    #[error("Failed to parse integer")]
+    ParseIntError(#[from] ParseIntError),
+    #[error("Failed to parse number")]
+    ParseFloatError(#[from] ParseFloatError),
+    #[error("Malformed XML")]
+    XmlError(#[from] roxmltree::Error),
+    /// The JSON is not well-formed.
+    #[error("Malformed JSON")]
+    JsonError(#[from] serde_json::Error),
+    #[error("Unsupported file extension")]
+    UnsupportedFileError,
+    #[error("Expected a different root element, but found <{found}>")]
+    WrongRootElementError { found: String },
+    #[error("Invalid gid '{0}'")]
+    InvalidGidError(String),
+    #[error("Unsupported layer encoding: {0}")]
+    UnsupportedEncodingError(String),
+    #[error("'{0}' uses a comma as its decimal separator, so the file was likely saved with the wrong locale")]
+    CommaDecimalError(String),
+    #[error("Tileset first gids must be nonzero and increasing, but {previous} is followed by {next}")]
+    InvalidTilesetOrderError { previous: u32, next: u32 },
+    #[error("A tile layer region of {width}x{height} tiles is too large to allocate")]
+    LayerTooLargeError { width: u32, height: u32 },
+    #[error("Several tilesets have the first gid {0}, so their tiles cannot be told apart")]
+    DuplicateFirstGidError(u32),
+    #[error("Wang set '{wang_set}' has a wang tile with a missing tile or color, for tile {tile_id}")]
+    InvalidWangTileError { wang_set: String, tile_id: u32 },
 }
 
-impl From<ParseBoolError> for Error {
-    fn from(_value: ParseBoolError) -> Self {
-        Self::ParsingError
-    }
-}
+pub type Result<T> = std::result::Result<T, Error>;
 
-impl From<ParseIntError> for Error {
-    fn from(_value: ParseIntError) -> Self {
-        Self::ParsingError
-    }
-}
+#[cfg(test)]
+mod test {
+    use std::error::Error as _;
+    use crate::{Error, Map};
 
-impl From<ParseFloatError> for Error {
-    fn from(_value: ParseFloatError) -> Self {
-        Self::ParsingError
-    }
-}
+    #[test]
+    fn test_source() {
+        let xml = r#"<map orientation="orthogonal" width="ten" height="10" tilewidth="20" tileheight="20"/>"#;
+        let error = Map::parse_str(xml).unwrap_err();
+        assert!(matches!(error, Error::ParseIntError(_)));
+        let source = error.source().unwrap();
+        assert!(source.downcast_ref::<std::num::ParseIntError>().is_some());
 
-impl From<roxmltree::Error> for Error {
-    fn from(_value: roxmltree::Error) -> Self {
-        Self::XmlParsingError
-    }
-}
+        let error = Map::parse_str("<map>").unwrap_err();
+        assert!(error.source().unwrap().downcast_ref::<roxmltree::Error>().is_some());
+
+        let error = Map::parse_json_str("{").unwrap_err();
+        assert!(error.source().unwrap().downcast_ref::<serde_json::Error>().is_some());
+        let error = Map::parse_json_str(r#"{ "width": "ten" }"#).unwrap_err();
+        assert!(matches!(error, Error::JsonParsingError));
 
-impl From<serde_json::Error> for Error {
-    fn from(_value: serde_json::Error) -> Self {
-        Self::JsonParsingError
+        let error = Map::parse_prefix(b"<map>").unwrap_err();
+        assert!(matches!(error, Error::XmlError(_)));
+
+        assert!(Error::ParsingError.source().is_none());
     }
 }
-
-pub type Result<T> = std::result::Result<T, Error>;
\ No newline at end of file
@@ -1,4 +1,5 @@
-use crate::Result;
+use std::path::{Path, PathBuf};
+use crate::{json_str, json_u32, resolve_path, JsonObject, Result};
 use roxmltree::Node;
 
 /// Image in an [`ImageLayer`](crate::ImageLayer), a [`Tileset`](crate::Tileset) or a [`Tile`](crate::Tile).
@@ -18,6 +19,13 @@ impl Image {
     pub fn width(&self) -> Option<u32> { self.width }
     pub fn height(&self) -> Option<u32> { self.height }
 
+    /// Path of the image file, joined against the directory of the file that declared the image.
+    /// That is the tileset's directory for tileset and tile images, and the map's directory for image layers.
+    /// Absolute sources are returned as-is.
+    pub fn resolve_source(&self, base_dir: &Path) -> PathBuf {
+        resolve_path(base_dir, &self.source)
+    }
+
     pub(crate) fn parse(image_node: Node) -> Result<Image> {
         let mut image = Image::default();
         for attribute in image_node.attributes() {
@@ -34,4 +42,58 @@ impl Image {
         }
         Ok(image)
     }
-}
\ No newline at end of file
+
+    /// Parses the image fields of a JSON layer, tileset or tile.
+    /// None if the object does not reference an image.
+    pub(crate) fn parse_json(object: &JsonObject) -> Result<Option<Image>> {
+        if !object.contains_key("image") {
+            return Ok(None);
+        }
+        let mut image = Image::default();
+        for (name, value) in object {
+            match name.as_str() {
+                "image" => image.source = json_str(value)?.into(),
+                "transparentcolor" => {
+                    let trans = json_str(value)?;
+                    image.trans = Some(trans.strip_prefix('#').unwrap_or(trans).into());
+                },
+                "imagewidth" => image.width = Some(json_u32(value)?),
+                "imageheight" => image.height = Some(json_u32(value)?),
+                _ => {}
+            }
+        }
+        Ok(Some(image))
+    }
+
+    /// Writes the image fields of a JSON layer, tileset or tile, as read by [`parse_json`](Self::parse_json).
+    pub(crate) fn write_json(&self, object: &mut JsonObject) {
+        object.insert("image".into(), self.source.as_str().into());
+        if let Some(trans) = &self.trans {
+            object.insert("transparentcolor".into(), format!("#{trans}").into());
+        }
+        if let Some(width) = self.width {
+            object.insert("imagewidth".into(), width.into());
+        }
+        if let Some(height) = self.height {
+            object.insert("imageheight".into(), height.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::path::Path;
+    use crate::Map;
+
+    #[test]
+    fn test_resolve_source() {
+        let map = Map::parse_str(include_str!("test_data/infinite.tmx")).unwrap();
+        let image = map.all_layers()
+            .find_map(|layer| layer.as_image_layer())
+            .unwrap()
+            .image();
+        assert_eq!("images/pepe.png", image.source());
+        let base_dir = Path::new("assets").join("maps");
+        assert_eq!(base_dir.join("images").join("pepe.png"), image.resolve_source(&base_dir));
+    }
+}
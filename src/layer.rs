@@ -1,11 +1,13 @@
+use std::fmt;
 use std::io::Read;
 use base64::prelude::*;
 use roxmltree::Node;
-use crate::{parse_bool, Color, Error, Gid, Image, ObjectGroupLayer, Properties, Result};
+use serde_json::{json, Value};
+use crate::{json_array, json_bool, json_f32, json_i32, json_object, json_str, json_u32, parse_bool, Color, Error, Gid, Image, JsonObject, Object, ObjectGroupLayer, Properties, RenderOrder, Result};
 
 
 /// A layer in a [`Map`](crate::map::Map).
-#[derive(Debug)]
+#[derive(PartialEq, Debug)]
 pub struct Layer {
     id: u32,
     name: String,
@@ -31,6 +33,8 @@ impl Layer {
     pub fn offset_y(&self) -> f32 { self.offset_y }
     pub fn parallax_x(&self) -> f32 { self.parallax_x }
     pub fn parallax_y(&self) -> f32 { self.parallax_y }
+    pub fn offset(&self) -> (f32, f32) { (self.offset_x, self.offset_y) }
+    pub fn parallax(&self) -> (f32, f32) { (self.parallax_x, self.parallax_y) }
     pub fn opacity(&self) -> f32 { self.opacity }
     pub fn visible(&self) -> bool { self.visible }
     pub fn locked(&self) -> bool { self.locked }
@@ -56,6 +60,12 @@ impl Layer {
         }
     }
 
+    /// Short name of the layer's kind, for display purposes.
+    /// See [`LayerKind::name`].
+    pub fn kind_name(&self) -> &'static str {
+        self.kind.name()
+    }
+
     pub fn as_tile_layer(&self) -> Option<&TileLayer> {
         self.kind.as_tile_layer()
     }
@@ -72,15 +82,15 @@ impl Layer {
         self.kind.as_object_group_layer()
     }
 
-    pub(crate) fn parse_tile_layer(tile_layer_node: Node, infinite: bool) -> Result<Self> {
+    pub(crate) fn parse_tile_layer(tile_layer_node: Node, context: ParseContext) -> Result<Self> {
         let fields = CommonLayerFields::parse(tile_layer_node)?;
-        let kind = LayerKind::TileLayer(TileLayer::parse(tile_layer_node, infinite)?);
+        let kind = LayerKind::TileLayer(TileLayer::parse(tile_layer_node, context)?);
         Ok(Self::new(fields, kind))
     }
 
-    pub(crate) fn parse_group_layer(group_node: Node, infinite: bool) -> Result<Self> {
+    pub(crate) fn parse_group_layer(group_node: Node, context: ParseContext) -> Result<Self> {
         let fields = CommonLayerFields::parse(group_node)?;
-        let kind = LayerKind::GroupLayer(GroupLayer::parse(group_node, infinite)?);
+        let kind = LayerKind::GroupLayer(GroupLayer::parse(group_node, context)?);
         Ok(Self::new(fields, kind))
     }
 
@@ -95,10 +105,76 @@ impl Layer {
         let kind = LayerKind::ObjectGroupLayer(ObjectGroupLayer::parse(object_group_layer_node)?);
         Ok(Self::new(fields, kind))
     }
+
+    pub(crate) fn parse_json(layer_value: &Value, context: ParseContext) -> Result<Self> {
+        let layer_object = json_object(layer_value)?;
+        let fields = CommonLayerFields::parse_json(layer_object)?;
+        let typ = layer_object.get("type").ok_or(Error::InvalidLayerError)?;
+        let kind = match json_str(typ)? {
+            "tilelayer" => LayerKind::TileLayer(TileLayer::parse_json(layer_object, context)?),
+            "group" => LayerKind::GroupLayer(GroupLayer::parse_json(layer_object, context)?),
+            "imagelayer" => LayerKind::ImageLayer(ImageLayer::parse_json(layer_object)?),
+            "objectgroup" => LayerKind::ObjectGroupLayer(ObjectGroupLayer::parse_json(layer_object)?),
+            _ => return Err(Error::InvalidLayerError),
+        };
+        Ok(Self::new(fields, kind))
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("id".into(), self.id.into());
+        object.insert("name".into(), self.name.as_str().into());
+        if !self.class.is_empty() {
+            object.insert("class".into(), self.class.as_str().into());
+        }
+        object.insert("offsetx".into(), self.offset_x.into());
+        object.insert("offsety".into(), self.offset_y.into());
+        object.insert("parallaxx".into(), self.parallax_x.into());
+        object.insert("parallaxy".into(), self.parallax_y.into());
+        object.insert("opacity".into(), self.opacity.into());
+        object.insert("visible".into(), self.visible.into());
+        object.insert("locked".into(), self.locked.into());
+        if self.tint_color != Color::default() {
+            object.insert("tintcolor".into(), self.tint_color.to_string().into());
+        }
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        let typ = match &self.kind {
+            LayerKind::TileLayer(tile_layer) => {
+                tile_layer.write_json(&mut object);
+                "tilelayer"
+            },
+            LayerKind::GroupLayer(group_layer) => {
+                group_layer.write_json(&mut object);
+                "group"
+            },
+            LayerKind::ImageLayer(image_layer) => {
+                image_layer.write_json(&mut object);
+                "imagelayer"
+            },
+            LayerKind::ObjectGroupLayer(object_group_layer) => {
+                object_group_layer.write_json(&mut object);
+                "objectgroup"
+            },
+        };
+        object.insert("type".into(), typ.into());
+        Value::Object(object)
+    }
+}
+
+/// Map attributes that layers depend on while parsing.
+#[derive(Copy, Clone, Default, Debug)]
+pub(crate) struct ParseContext {
+    pub infinite: bool,
+    /// Width of the map, which tile layers inherit when they omit their own.
+    pub width: u32,
+    /// Height of the map, which tile layers inherit when they omit their own.
+    pub height: u32,
 }
 
 /// A specific type of [`Layer`].
-#[derive(Debug)]
+#[derive(PartialEq, Debug)]
 pub enum LayerKind {
     TileLayer(TileLayer),
     GroupLayer(GroupLayer),
@@ -107,6 +183,16 @@ pub enum LayerKind {
 }
 
 impl LayerKind {
+    /// Short name of the kind: "tile", "group", "image" or "object".
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayerKind::TileLayer(_) => "tile",
+            LayerKind::GroupLayer(_) => "group",
+            LayerKind::ImageLayer(_) => "image",
+            LayerKind::ObjectGroupLayer(_) => "object",
+        }
+    }
+
     pub fn as_tile_layer(&self) -> Option<&TileLayer> {
         match self {
             LayerKind::TileLayer(tile_layer) => Some(tile_layer),
@@ -137,12 +223,14 @@ impl LayerKind {
 }
 
 /// A layer of [`Gid`]s of [`Tile`](crate::Tile)s.
-#[derive(Debug, Default)]
+#[derive(PartialEq, Default)]
 pub struct TileLayer {
+    infinite: bool,
     width: u32,
     height: u32,
     region: TileLayerRegion,
     tile_gids: Vec<Gid>,
+    non_null_count: usize,
 }
 
 impl TileLayer {
@@ -152,18 +240,30 @@ impl TileLayer {
 
     /// Supposed height of the tile layer.
     /// Meaningless in an infinite map and should not be programmed against.
-    pub fn height(&self) -> u32 { self.width }
+    pub fn height(&self) -> u32 { self.height }
+
+    /// Width and height of the layer, in tiles.
+    /// None in an infinite map, where [`region`](Self::region) should be used instead.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self.infinite {
+            true => None,
+            false => Some((self.region.width, self.region.height)),
+        }
+    }
 
     /// A rectangular region that encompases all tiles in the layer.
     /// Useful when manual iteration over tiles is required.
     pub fn region(&self) -> TileLayerRegion { self.region }
 
+    /// True if every gid in the layer is null.
+    pub fn is_empty(&self) -> bool { self.non_null_count == 0 }
+
     /// Gets the [`Gid`] of the tile at the specified coordinates.
     pub fn gid_at(&self, x: i32, y: i32) -> Gid {
-        let x = x - self.region.x;
-        let y = y - self.region.y;
-        let region_width = self.region.width as i32;
-        let region_height = self.region.height as i32;
+        let x = i64::from(x) - i64::from(self.region.x);
+        let y = i64::from(y) - i64::from(self.region.y);
+        let region_width = i64::from(self.region.width);
+        let region_height = i64::from(self.region.height);
         if x < 0 || x >= region_width {
             return Gid::default();
         }
@@ -173,6 +273,44 @@ impl TileLayer {
         self.tile_gids[(y * region_width + x) as usize]
     }
 
+    /// Sets the [`Gid`] of the tile at the specified coordinates.
+    /// Painting outside of the [`region`](Self::region) grows it to include the tile, in any direction.
+    /// Growing reallocates and copies every gid in the layer, so it is best to paint the farthest tiles first.
+    /// Cells added by growth are null.
+    /// Fails with [`Error::LayerTooLargeError`], leaving the layer unchanged, if the grown region cannot be allocated.
+    pub fn set_gid(&mut self, x: i32, y: i32, gid: Gid) -> Result<()> {
+        let tile = TileLayerRegion { x, y, width: 1, height: 1 };
+        let region = self.region.union(tile);
+        let index_of = |x: i32, y: i32| {
+            let row = (i64::from(y) - i64::from(region.y)) as usize;
+            let column = (i64::from(x) - i64::from(region.x)) as usize;
+            row * region.width as usize + column
+        };
+        if region != self.region {
+            let too_large = || Error::LayerTooLargeError { width: region.width, height: region.height };
+            let cell_count = (region.width as usize)
+                .checked_mul(region.height as usize)
+                .ok_or_else(too_large)?;
+            let mut tile_gids = Vec::new();
+            tile_gids.try_reserve_exact(cell_count).map_err(|_| too_large())?;
+            tile_gids.resize(cell_count, Gid::NULL);
+            for (old_x, old_y, old_gid) in self.gids() {
+                tile_gids[index_of(old_x, old_y)] = old_gid;
+            }
+            self.tile_gids = tile_gids;
+            self.region = region;
+        }
+        let cell = &mut self.tile_gids[index_of(x, y)];
+        if *cell != Gid::NULL {
+            self.non_null_count -= 1;
+        }
+        if gid != Gid::NULL {
+            self.non_null_count += 1;
+        }
+        *cell = gid;
+        Ok(())
+    }
+
     /// Iterates over all gids in the layer, including null ones.
     /// Includes x, y coordinates (in tiles) of each tile.
     pub fn gids(&self) -> Gids<'_> {
@@ -188,8 +326,78 @@ impl TileLayer {
         }
     }
 
-    pub(crate) fn parse(layer_node: Node, infinite: bool) -> Result<Self> {
-        let mut result = Self::default();
+    /// Iterates over all gids in the layer, including null ones, in the scan order given.
+    /// Includes x, y coordinates (in tiles) of each tile.
+    pub fn gids_ordered(&self, order: RenderOrder) -> impl Iterator<Item = (i32, i32, Gid)> + '_ {
+        let region = self.region;
+        let width = region.width as i32;
+        let height = region.height as i32;
+        let (right, down) = match order {
+            RenderOrder::RightDown => (true, true),
+            RenderOrder::RightUp => (true, false),
+            RenderOrder::LeftDown => (false, true),
+            RenderOrder::LeftUp => (false, false),
+        };
+        (0..height)
+            .map(move |i| if down { i } else { height - 1 - i })
+            .flat_map(move |y| (0..width)
+                .map(move |i| if right { i } else { width - 1 - i })
+                .map(move |x| (region.x + x, region.y + y))
+                .map(|(x, y)| (x, y, self.gid_at(x, y)))
+            )
+    }
+
+    /// Coordinates of the chunk containing the tile at x, y, for chunks of the given size.
+    /// Chunk 0, 0 starts at tile 0, 0, and chunk -1, -1 ends just before it.
+    /// None if a chunk dimension is 0 or does not fit in an `i32`.
+    pub fn chunk_of(x: i32, y: i32, chunk_width: u32, chunk_height: u32) -> Option<(i32, i32)> {
+        let chunk_width = i32::try_from(chunk_width).ok().filter(|&width| width > 0)?;
+        let chunk_height = i32::try_from(chunk_height).ok().filter(|&height| height > 0)?;
+        Some((x.div_euclid(chunk_width), y.div_euclid(chunk_height)))
+    }
+
+    /// Iterates over all gids in a chunk, including null ones.
+    /// Includes x, y coordinates (in tiles) of each tile.
+    /// See [`chunk_of`](Self::chunk_of) for how chunks are laid out.
+    pub fn tiles_in_chunk(&self, chunk_x: i32, chunk_y: i32, chunk_width: u32, chunk_height: u32) -> impl Iterator<Item = (i32, i32, Gid)> + '_ {
+        let start_x = chunk_x * chunk_width as i32;
+        let start_y = chunk_y * chunk_height as i32;
+        let end_x = start_x + chunk_width as i32;
+        let end_y = start_y + chunk_height as i32;
+        (start_y..end_y).flat_map(move |y| (start_x..end_x).map(move |x| (x, y, self.gid_at(x, y))))
+    }
+
+    /// Decodes the text of a `<data>` or `<chunk>` element into a buffer of gids, as done when parsing layers.
+    /// Encoding and compression are the attributes of the `<data>` element.
+    ///
+    /// The buffer is cleared first, but keeps its allocation,
+    /// so one buffer can be reused for decoding many layers, such as when repeatedly reloading maps.
+    /// Capacity is never released, so the buffer stays as large as the largest layer decoded into it.
+    pub fn decode_into(data: &str, encoding: Option<&str>, compression: Option<&str>, gids: &mut Vec<Gid>) -> Result<()> {
+        gids.clear();
+        parse_tile_gids(data.trim(), encoding, compression, gids)
+    }
+
+    /// Fails unless a finite layer has exactly one gid per tile, since lookups index into the gids without checks.
+    fn check_finite_size(&self) -> Result<()> {
+        if self.tile_gids.len() as u64 != u64::from(self.width) * u64::from(self.height) {
+            return Err(Error::InvalidLayerError);
+        }
+        Ok(())
+    }
+
+    /// Caches the number of non-null gids once they are all parsed.
+    fn count_non_null(&mut self) {
+        self.non_null_count = self.tile_gids.iter().filter(|gid| **gid != Gid::NULL).count();
+    }
+
+    pub(crate) fn parse(layer_node: Node, context: ParseContext) -> Result<Self> {
+        let mut result = Self {
+            infinite: context.infinite,
+            width: context.width,
+            height: context.height,
+            ..Self::default()
+        };
         for attr in layer_node.attributes() {
             match attr.name() {
                 "width" => result.width = attr.value().parse()?,
@@ -198,12 +406,105 @@ impl TileLayer {
             }
         }
         let data_node = layer_node.first_element_child().ok_or(Error::InvalidLayerError)?;
-        match infinite {
+        match context.infinite {
             true => parse_infinite_layer_data(&mut result, data_node)?,
             false => parse_finite_layer_data(&mut result, data_node)?,
         };
+        result.count_non_null();
+        Ok(result)
+    }
+
+    pub(crate) fn parse_json(layer_object: &JsonObject, context: ParseContext) -> Result<Self> {
+        let mut result = Self {
+            infinite: context.infinite,
+            width: context.width,
+            height: context.height,
+            ..Self::default()
+        };
+        let mut encoding = None;
+        let mut compression = None;
+        for (name, value) in layer_object {
+            match name.as_str() {
+                "width" => result.width = json_u32(value)?,
+                "height" => result.height = json_u32(value)?,
+                "encoding" => encoding = Some(json_str(value)?),
+                "compression" => compression = Some(json_str(value)?),
+                _ => {}
+            }
+        }
+        match context.infinite {
+            true => {
+                let chunks_value = layer_object.get("chunks").ok_or(Error::InvalidLayerError)?;
+                let mut chunks = Vec::new();
+                for chunk_value in json_array(chunks_value)? {
+                    let chunk_object = json_object(chunk_value)?;
+                    let mut x: i32 = 0;
+                    let mut y: i32 = 0;
+                    let mut width: u32 = 0;
+                    let mut height: u32 = 0;
+                    let mut tile_gids = Vec::new();
+                    for (name, value) in chunk_object {
+                        match name.as_str() {
+                            "x" => x = json_i32(value)?,
+                            "y" => y = json_i32(value)?,
+                            "width" => width = json_u32(value)?,
+                            "height" => height = json_u32(value)?,
+                            "data" => tile_gids = parse_json_tile_gids(value, encoding, compression)?,
+                            _ => {}
+                        }
+                    }
+                    chunks.push(Chunk::new(x, y, width, height, tile_gids));
+                }
+                composite_chunks(&mut result, chunks);
+            },
+            false => {
+                let data_value = layer_object.get("data").ok_or(Error::InvalidLayerError)?;
+                result.tile_gids = parse_json_tile_gids(data_value, encoding, compression)?;
+                result.region = TileLayerRegion { x: 0, y: 0, width: result.width, height: result.height };
+                result.check_finite_size()?;
+            },
+        }
+        result.count_non_null();
         Ok(result)
     }
+
+    /// Writes the layer's gids into a JSON layer object, as an unencoded array.
+    /// Infinite layers are written as a single chunk covering their region.
+    fn write_json(&self, layer_object: &mut JsonObject) {
+        layer_object.insert("width".into(), self.width.into());
+        layer_object.insert("height".into(), self.height.into());
+        let data: Value = self.tile_gids.iter().map(|gid| gid.0).collect();
+        match self.infinite {
+            true => {
+                let region = self.region;
+                let chunk = json!({
+                    "x": region.x,
+                    "y": region.y,
+                    "width": region.width,
+                    "height": region.height,
+                    "data": data,
+                });
+                layer_object.insert("chunks".into(), json!([chunk]));
+            },
+            false => {
+                layer_object.insert("data".into(), data);
+            },
+        }
+    }
+}
+
+/// Summarizes the layer instead of listing every gid, which would be unreadable for large layers.
+/// Gids remain available via [`TileLayer::gids`].
+impl fmt::Debug for TileLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TileLayer")
+            .field("infinite", &self.infinite)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("region", &self.region)
+            .field("non_null_count", &self.non_null_count)
+            .finish_non_exhaustive()
+    }
 }
 
 /// Iterator over gids in a [`TileLayer`].
@@ -228,7 +529,7 @@ impl<'a> Gids<'a> {
 impl<'a> Iterator for Gids<'a> {
     type Item = (i32, i32, Gid);
     fn next(&mut self) -> Option<Self::Item> {
-        if self.idx >= self.total as usize {
+        if self.idx >= self.total {
             return None;
         }
         let next_x = self.x as i32 + self.off_x;
@@ -249,7 +550,7 @@ pub struct NonNullGids<'a>(Gids<'a>);
 impl<'a> Iterator for NonNullGids<'a> {
     type Item = (i32, i32, Gid);
     fn next(&mut self) -> Option<Self::Item> {
-        while let Some((x, y, gid)) = self.0.next() {
+        for (x, y, gid) in self.0.by_ref() {
             if gid != Gid::NULL {
                 return Some((x, y, gid));
             }
@@ -268,23 +569,196 @@ pub struct TileLayerRegion {
     pub height: u32,
 }
 
+impl TileLayerRegion {
+
+    /// True if the region contains no tiles.
+    pub fn is_empty(self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// Smallest region containing both regions.
+    /// Empty regions are ignored.
+    /// Dimensions saturate at `u32::MAX`, which only a region spanning every `i32` coordinate would exceed.
+    pub fn union(self, other: Self) -> Self {
+        if self.is_empty() { return other }
+        if other.is_empty() { return self }
+        let min_x = self.x.min(other.x);
+        let min_y = self.y.min(other.y);
+        let max_x = (i64::from(self.x) + i64::from(self.width)).max(i64::from(other.x) + i64::from(other.width));
+        let max_y = (i64::from(self.y) + i64::from(self.height)).max(i64::from(other.y) + i64::from(other.height));
+        Self {
+            x: min_x,
+            y: min_y,
+            width: u32::try_from(max_x - i64::from(min_x)).unwrap_or(u32::MAX),
+            height: u32::try_from(max_y - i64::from(min_y)).unwrap_or(u32::MAX),
+        }
+    }
+}
+
+/// Finds a tile layer by id, searching nested groups as well.
+pub(crate) fn find_tile_layer_mut(layers: &mut [Layer], id: u32) -> Option<&mut TileLayer> {
+    for layer in layers {
+        match &mut layer.kind {
+            LayerKind::TileLayer(tile_layer) if layer.id == id => return Some(tile_layer),
+            LayerKind::GroupLayer(group_layer) => {
+                if let Some(tile_layer) = find_tile_layer_mut(&mut group_layer.0, id) {
+                    return Some(tile_layer);
+                }
+            },
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Collects the objects of all object group layers, including those nested in groups.
+pub(crate) fn collect_objects_mut<'a>(layers: &'a mut [Layer], result: &mut Vec<&'a mut Object>) {
+    for layer in layers {
+        match &mut layer.kind {
+            LayerKind::ObjectGroupLayer(object_group_layer) => result.extend(object_group_layer.objects_mut()),
+            LayerKind::GroupLayer(group_layer) => collect_objects_mut(&mut group_layer.0, result),
+            _ => {}
+        }
+    }
+}
+
+/// Depth-first iterator over the [`Layer`]s in a [`Map`](crate::Map), including those nested in groups.
+pub struct AllLayers<'a> {
+    stack: Vec<std::slice::Iter<'a, Layer>>,
+}
+
+impl<'a> AllLayers<'a> {
+    pub(crate) fn new(layers: &'a [Layer]) -> Self {
+        Self { stack: vec![layers.iter()] }
+    }
+}
+
+impl<'a> Iterator for AllLayers<'a> {
+    type Item = &'a Layer;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(iter) = self.stack.last_mut() {
+            match iter.next() {
+                Some(layer) => {
+                    if let Some(group_layer) = layer.as_group_layer() {
+                        self.stack.push(group_layer.layers().iter());
+                    }
+                    return Some(layer);
+                },
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+        None
+    }
+}
+
+/// A [`Layer`] along with the fields it inherits from the groups containing it, as yielded by [`ComposedLayers`].
+/// Rendering a layer with these fields gives the same result as rendering it within its groups.
+#[derive(Copy, Clone, Debug)]
+pub struct ComposedLayer<'a> {
+    pub layer: &'a Layer,
+    /// Product of the opacities of the layer and its groups.
+    pub opacity: f32,
+    /// Product of the tint colors of the layer and its groups.
+    pub tint_color: Color,
+    /// Sum of the offsets of the layer and its groups.
+    pub offset: (f32, f32),
+    /// Product of the parallax factors of the layer and its groups.
+    pub parallax: (f32, f32),
+    /// False if the layer or any of its groups is hidden.
+    pub visible: bool,
+}
+
+impl<'a> ComposedLayer<'a> {
+    /// Fields of a layer outside of any group.
+    fn new(layer: &'a Layer) -> Self {
+        Self {
+            layer,
+            opacity: layer.opacity,
+            tint_color: layer.tint_color,
+            offset: layer.offset(),
+            parallax: layer.parallax(),
+            visible: layer.visible,
+        }
+    }
+
+    /// Fields of a child layer of this group.
+    fn child(self, layer: &'a Layer) -> Self {
+        Self {
+            layer,
+            opacity: self.opacity * layer.opacity,
+            tint_color: self.tint_color.multiply(layer.tint_color),
+            offset: (self.offset.0 + layer.offset_x, self.offset.1 + layer.offset_y),
+            parallax: (self.parallax.0 * layer.parallax_x, self.parallax.1 * layer.parallax_y),
+            visible: self.visible && layer.visible,
+        }
+    }
+}
+
+/// Depth-first iterator over the [`Layer`]s in a [`Map`](crate::Map), like [`AllLayers`],
+/// which composes the fields of group layers into those of their children.
+pub struct ComposedLayers<'a> {
+    stack: Vec<(std::slice::Iter<'a, Layer>, Option<ComposedLayer<'a>>)>,
+}
+
+impl<'a> ComposedLayers<'a> {
+    pub(crate) fn new(layers: &'a [Layer]) -> Self {
+        Self { stack: vec![(layers.iter(), None)] }
+    }
+}
+
+impl<'a> Iterator for ComposedLayers<'a> {
+    type Item = ComposedLayer<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((iter, group)) = self.stack.last_mut() {
+            match iter.next() {
+                Some(layer) => {
+                    let composed = match group {
+                        Some(group) => group.child(layer),
+                        None => ComposedLayer::new(layer),
+                    };
+                    if let Some(group_layer) = layer.as_group_layer() {
+                        self.stack.push((group_layer.layers().iter(), Some(composed)));
+                    }
+                    return Some(composed);
+                },
+                None => {
+                    self.stack.pop();
+                },
+            }
+        }
+        None
+    }
+}
+
 /// A layer containing other [`Layer`]s.
-#[derive(Default, Debug)]
+/// The group's own fields, like its opacity and offset, are those of the [`Layer`] containing it,
+/// and apply to all of its children. See [`ComposedLayers`] for the fields children end up with.
+#[derive(PartialEq, Default, Debug)]
 pub struct GroupLayer(Vec<Layer>);
 impl GroupLayer {
 
     pub fn layers(&self) -> &[Layer] { &self.0 }
 
-    pub(crate) fn parse(group_node: Node, infinite: bool) -> Result<Self> {
+    pub(crate) fn parse(group_node: Node, context: ParseContext) -> Result<Self> {
         let mut result = Self::default();
         for node in group_node.children() {
             match node.tag_name().name() {
                 "layer" => {
-                    let layer = Layer::parse_tile_layer(node, infinite)?;
+                    let layer = Layer::parse_tile_layer(node, context)?;
                     result.0.push(layer);
                 },
                 "group" => {
-                    let layer = Layer::parse_group_layer(node, infinite)?;
+                    let layer = Layer::parse_group_layer(node, context)?;
+                    result.0.push(layer)
+                },
+                "imagelayer" => {
+                    let layer = Layer::parse_image_layer(node)?;
+                    result.0.push(layer)
+                },
+                "objectgroup" => {
+                    let layer = Layer::parse_object_group_layer(node)?;
                     result.0.push(layer)
                 },
                 _ => {}
@@ -292,10 +766,24 @@ impl GroupLayer {
         }
         Ok(result)
     }
+
+    pub(crate) fn parse_json(group_object: &JsonObject, context: ParseContext) -> Result<Self> {
+        let mut result = Self::default();
+        if let Some(layers) = group_object.get("layers") {
+            for layer_value in json_array(layers)? {
+                result.0.push(Layer::parse_json(layer_value, context)?);
+            }
+        }
+        Ok(result)
+    }
+
+    fn write_json(&self, group_object: &mut JsonObject) {
+        group_object.insert("layers".into(), self.0.iter().map(Layer::to_json).collect());
+    }
 }
 
 /// A layer containing a single image.
-#[derive(Default, Debug)]
+#[derive(PartialEq, Default, Debug)]
 pub struct ImageLayer {
     repeat_x: bool,
     repeat_y: bool,
@@ -317,13 +805,33 @@ impl ImageLayer {
             }
         }
         for child in image_layer_node.children() {
-            match child.tag_name().name() {
-                "image" => result.image = Image::parse(child)?,
+            if child.tag_name().name() == "image" {
+                result.image = Image::parse(child)?;
+            }
+        }
+        Ok(result)
+    }
+
+    fn parse_json(image_layer_object: &JsonObject) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in image_layer_object {
+            match name.as_str() {
+                "repeatx" => result.repeat_x = json_bool(value)?,
+                "repeaty" => result.repeat_y = json_bool(value)?,
                 _ => {}
             }
         }
+        if let Some(image) = Image::parse_json(image_layer_object)? {
+            result.image = image;
+        }
         Ok(result)
     }
+
+    fn write_json(&self, image_layer_object: &mut JsonObject) {
+        image_layer_object.insert("repeatx".into(), self.repeat_x.into());
+        image_layer_object.insert("repeaty".into(), self.repeat_y.into());
+        self.image.write_json(image_layer_object);
+    }
 }
 
 /// 2D storage of tile gids in an infinite tile layer.
@@ -335,6 +843,18 @@ struct Chunk {
     tile_gids: Vec<Gid>,
 }
 
+impl Chunk {
+    fn new(x: i32, y: i32, width: u32, height: u32, tile_gids: Vec<Gid>) -> Self {
+        Self {
+            min_x: x,
+            min_y: y,
+            max_x: x + width as i32,
+            max_y: y + height as i32,
+            tile_gids,
+        }
+    }
+}
+
 /// Fields that all layer types have in common.
 struct CommonLayerFields {
     id: u32,
@@ -359,8 +879,8 @@ impl Default for CommonLayerFields {
             class: "".into(),
             offset_x: 0.0,
             offset_y: 0.0,
-            parallax_x: 0.0,
-            parallax_y: 0.0,
+            parallax_x: 1.0,
+            parallax_y: 1.0,
             opacity: 1.0,
             visible: true,
             locked: false,
@@ -377,7 +897,7 @@ impl CommonLayerFields {
             match attr.name() {
                 "id" => common.id = attr.value().parse()?,
                 "name" => common.name = attr.value().into(),
-                "class" => common.class = attr.value().into(),
+                "class" | "type" => common.class = attr.value().into(),
                 "offsetx" => common.offset_x = attr.value().parse()?,
                 "offsety" => common.offset_y = attr.value().parse()?,
                 "parallaxx" => common.parallax_x = attr.value().parse()?,
@@ -391,7 +911,29 @@ impl CommonLayerFields {
         }
         for child in layer_node.children() {
             if child.tag_name().name() == "properties" {
-                common.properties = Properties::parse(child)?;
+                common.properties.merge(Properties::parse(child)?);
+            }
+        }
+        Ok(common)
+    }
+
+    fn parse_json(layer_object: &JsonObject) -> Result<CommonLayerFields> {
+        let mut common = CommonLayerFields::default();
+        for (name, value) in layer_object {
+            match name.as_str() {
+                "id" => common.id = json_u32(value)?,
+                "name" => common.name = json_str(value)?.into(),
+                "class" => common.class = json_str(value)?.into(),
+                "offsetx" => common.offset_x = json_f32(value)?,
+                "offsety" => common.offset_y = json_f32(value)?,
+                "parallaxx" => common.parallax_x = json_f32(value)?,
+                "parallaxy" => common.parallax_y = json_f32(value)?,
+                "opacity" => common.opacity = json_f32(value)?,
+                "tintcolor" => common.tint_color = json_str(value)?.parse()?,
+                "visible" => common.visible = json_bool(value)?,
+                "locked" => common.locked = json_bool(value)?,
+                "properties" => common.properties = Properties::parse_json(value)?,
+                _ => {}
             }
         }
         Ok(common)
@@ -402,13 +944,32 @@ impl CommonLayerFields {
 fn parse_finite_layer_data(layer: &mut TileLayer, data_node: Node) -> Result<()> {
     let encoding = data_node.attribute("encoding");
     let compression = data_node.attribute("compression");
-    let tile_gids = data_node.text().ok_or(Error::InvalidLayerError)?.trim();
-    let tile_gids = parse_tile_gids(tile_gids, encoding, compression)?;
-    let tile_gids = tile_gids.into_iter().map(|gid_int| Gid(gid_int)).collect();
-    layer.tile_gids = tile_gids;
-    layer.region.width = layer.width;
-    layer.region.height = layer.height;
-    Ok(())
+    let tile_gids = data_node.text().unwrap_or_default().trim();
+    let has_tile_elements = data_node.children().any(|child| child.has_tag_name("tile"));
+    layer.tile_gids = match (has_tile_elements, tile_gids.is_empty()) {
+        (true, _) if encoding.is_none() && compression.is_none() => data_node
+            .children()
+            .filter(|child| child.has_tag_name("tile"))
+            .map(parse_tile_element)
+            .collect::<Result<_>>()?,
+        (false, true) => vec![Gid::NULL; (layer.width * layer.height) as usize],
+        _ => {
+            let mut gids = Vec::new();
+            parse_tile_gids(tile_gids, encoding, compression, &mut gids)?;
+            gids
+        },
+    };
+    layer.region = TileLayerRegion { x: 0, y: 0, width: layer.width, height: layer.height };
+    layer.check_finite_size()
+}
+
+/// Gid of a `<tile>` element, which old versions of Tiled write for each tile of unencoded layer data.
+/// A missing gid means the tile is empty.
+fn parse_tile_element(tile_node: Node) -> Result<Gid> {
+    match tile_node.attribute("gid") {
+        Some(gid) => gid.parse().map(Gid).map_err(|_| Error::InvalidGidError(gid.into())),
+        None => Ok(Gid::NULL),
+    }
 }
 
 /// Parses tiles in an infinite layer's data node.
@@ -418,10 +979,6 @@ fn parse_infinite_layer_data(layer: &mut TileLayer, data_node: Node) -> Result<(
 
     // Collects chunks
     let mut chunks = Vec::new();
-    let mut global_min_x = i32::MAX;
-    let mut global_min_y = i32::MAX;
-    let mut global_max_x = i32::MIN;
-    let mut global_max_y = i32::MIN;
     for chunk_node in data_node.children() {
         if !chunk_node.has_tag_name("chunk") { continue };
         let mut x: i32 = 0;
@@ -436,21 +993,35 @@ fn parse_infinite_layer_data(layer: &mut TileLayer, data_node: Node) -> Result<(
                 "height" => height = attr.value().parse()?,
                 _ => {}
             }
-            let x2 = x + width as i32;
-            let y2 = y + height as i32;
-            global_min_x = global_min_x.min(x);
-            global_min_y = global_min_y.min(y);
-            global_max_x = global_max_x.max(x2);
-            global_max_y = global_max_y.max(y2);
-        }
-        let max_x = x + width as i32;
-        let max_y = y + height as i32;
+        }
         let tile_gids = chunk_node
             .text()
             .ok_or(Error::InvalidLayerError)?.trim();
-        let tile_gids = parse_tile_gids(tile_gids, encoding, compression)?;
-        let tile_gids: Vec<Gid> = tile_gids.into_iter().map(|gid_int| Gid(gid_int)).collect();
-        chunks.push(Chunk { min_x: x, min_y: y, max_x, max_y, tile_gids });
+        let mut gids = Vec::new();
+        parse_tile_gids(tile_gids, encoding, compression, &mut gids)?;
+        chunks.push(Chunk::new(x, y, width, height, gids));
+    }
+    composite_chunks(layer, chunks);
+    Ok(())
+}
+
+/// Composites the chunks of an infinite layer into a single region.
+/// Without chunks, the layer is left with an empty region at the origin.
+fn composite_chunks(layer: &mut TileLayer, chunks: Vec<Chunk>) {
+    if chunks.is_empty() {
+        return;
+    }
+
+    // Computes region that encompasses all chunks.
+    let mut global_min_x = i32::MAX;
+    let mut global_min_y = i32::MAX;
+    let mut global_max_x = i32::MIN;
+    let mut global_max_y = i32::MIN;
+    for chunk in &chunks {
+        global_min_x = global_min_x.min(chunk.min_x);
+        global_min_y = global_min_y.min(chunk.min_y);
+        global_max_x = global_max_x.max(chunk.max_x);
+        global_max_y = global_max_y.max(chunk.max_y);
     }
 
     // Allocates vec to fit tile gids in all chunks.
@@ -471,7 +1042,7 @@ fn parse_infinite_layer_data(layer: &mut TileLayer, data_node: Node) -> Result<(
                 let chunk_idx = {
                     let chunk_x = global_x - chunk.min_x;
                     let chunk_y = global_y - chunk.min_y;
-                    (chunk_y * chunk_width as i32 + chunk_x) as usize
+                    (chunk_y * chunk_width + chunk_x) as usize
                 };
                 raw_tile_gids[raw_idx] = chunk.tile_gids[chunk_idx];
             }
@@ -484,64 +1055,374 @@ fn parse_infinite_layer_data(layer: &mut TileLayer, data_node: Node) -> Result<(
     layer.region.y = global_min_y;
     layer.region.width = raw_width;
     layer.region.height = raw_height;
-    Ok(())
 }
 
-fn parse_tile_gids(layer_data: &str, encoding: Option<&str>, compression: Option<&str>) -> Result<Vec<u32>> {
+/// Parses tile gids stored in a JSON layer or chunk.
+/// Either an array of integers, or an encoded string.
+fn parse_json_tile_gids(data: &Value, encoding: Option<&str>, compression: Option<&str>) -> Result<Vec<Gid>> {
+    match data {
+        Value::String(data) => {
+            let mut gids = Vec::new();
+            parse_tile_gids(data.trim(), encoding, compression, &mut gids)?;
+            Ok(gids)
+        },
+        _ => json_array(data)?
+            .iter()
+            .map(|gid| json_u32(gid).map(Gid))
+            .collect(),
+    }
+}
+
+/// Appends the gids of a layer or chunk to a buffer.
+fn parse_tile_gids(layer_data: &str, encoding: Option<&str>, compression: Option<&str>, result: &mut Vec<Gid>) -> Result<()> {
     match (encoding, compression) {
         (Some("csv"), None) => {
-            let parsed = parse_csv(layer_data)?;
-            Ok(parsed)
+            parse_csv(layer_data, result)
+        },
+        (Some("csv"), Some(compression)) => {
+            let message = format!("CSV data cannot be compressed, but found compression '{compression}'");
+            Err(Error::UnsupportedEncodingError(message))
         },
         (Some("base64"), None) => {
             let decoded = decode_base64(layer_data.as_bytes())?;
-            let parsed = parse_bytes(decoded.as_slice())?;
-            Ok(parsed)
+            parse_undeclared_bytes(decoded.as_slice(), result)
         },
         #[cfg(feature = "flate2")]
         (Some("base64"), Some("gzip")) => {
             let decoded = decode_base64(layer_data.as_bytes()).map_err(|_| Error::DecodeLayerError)?;
             let decompressed = flate2::read::GzDecoder::new(decoded.as_slice());
-            let parsed = parse_bytes(decompressed)?;
-            Ok(parsed)
+            parse_bytes(decompressed, result)
         },
         #[cfg(feature = "flate2")]
         (Some("base64"), Some("zlib")) => {
             let decoded = decode_base64(layer_data.as_bytes()).map_err(|_| Error::DecodeLayerError)?;
             let decompressed = flate2::read::ZlibDecoder::new(decoded.as_slice());
-            let parsed = parse_bytes(decompressed)?;
-            Ok(parsed)
+            parse_bytes(decompressed, result)
         },
         #[cfg(feature = "zstd")]
         (Some("base64"), Some("zstd")) => {
             let decoded = decode_base64(layer_data.as_bytes())?;
             let decompressed = zstd::stream::Decoder::new(decoded.as_slice()).map_err(|_| Error::DecodeLayerError)?;
-            let parsed = parse_bytes(decompressed)?;
-            Ok(parsed)
+            parse_bytes(decompressed, result)
+        },
+        // Some exporters omit the encoding of CSV data.
+        // A single gid has no commas, so any data made of digits is accepted.
+        (None, None) if is_csv(layer_data) => {
+            parse_csv(layer_data, result)
         },
-        _ => return Err(Error::DecodeLayerError),
+        _ => Err(Error::DecodeLayerError),
     }
 }
 
-fn parse_csv(csv: &str) -> Result<Vec<u32>> {
-    let mut result: Vec<u32> = Vec::new();
+fn is_csv(layer_data: &str) -> bool {
+    layer_data.contains(|c: char| c.is_ascii_digit()) && layer_data
+        .chars()
+        .all(|c| c.is_ascii_digit() || c == ',' || c.is_ascii_whitespace())
+}
+
+/// Gids are parsed as u32, since flip flags occupy the high bits.
+/// A trailing comma after the last gid is tolerated.
+fn parse_csv(csv: &str, result: &mut Vec<Gid>) -> Result<()> {
+    let csv = csv.trim_end();
+    let csv = csv.strip_suffix(',').unwrap_or(csv);
+    // Sized up front, since large layers would otherwise reallocate many times.
+    let gid_count = csv.bytes().filter(|&byte| byte == b',').count() + 1;
+    result.reserve_exact(gid_count);
     for s in csv.split(',') {
         let s = s.trim();
-        result.push(s.parse()?)
+        result.push(Gid(s.parse::<u32>()?))
     }
-    Ok(result)
+    Ok(())
 }
 
 fn decode_base64(encoded_bytes: &[u8]) -> Result<Vec<u8>> {
-    BASE64_STANDARD.decode(&encoded_bytes).map_err(|_| Error::DecodeLayerError)
+    BASE64_STANDARD.decode(encoded_bytes).map_err(|_| Error::DecodeLayerError)
+}
+
+/// Parses decoded data that declares no compression.
+/// Some broken exporters omit the compression of gzip or zlib data, so it is detected from its magic bytes.
+/// Uncompressed gids may start with the same bytes, so data that fails to decompress is read as is.
+fn parse_undeclared_bytes(bytes: &[u8], result: &mut Vec<Gid>) -> Result<()> {
+    #[cfg(feature = "flate2")]
+    {
+        let start = result.len();
+        let decompressed = match bytes {
+            [0x1F, 0x8B, ..] => Some(parse_bytes(flate2::read::GzDecoder::new(bytes), result)),
+            [0x78, 0x01 | 0x9C | 0xDA, ..] => Some(parse_bytes(flate2::read::ZlibDecoder::new(bytes), result)),
+            _ => None,
+        };
+        if let Some(Ok(())) = decompressed {
+            return Ok(());
+        }
+        result.truncate(start);
+    }
+    parse_bytes(bytes, result)
 }
 
-fn parse_bytes(mut read: impl Read) -> Result<Vec<u32>> {
-    let mut result: Vec<u32> = Vec::new();
+fn parse_bytes(mut read: impl Read, result: &mut Vec<Gid>) -> Result<()> {
     let mut bytes: [u8; 4] = [0; 4];
     while read.read(&mut bytes)? != 0 {
         let tile_gid = u32::from_le_bytes(bytes);
-        result.push(tile_gid);
+        result.push(Gid(tile_gid));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod test {
+    use base64::prelude::*;
+    use super::parse_csv;
+    use crate::{Error, Gid, Map, RenderOrder, TileLayer, TileLayerRegion};
+
+    #[test]
+    fn test_parse_csv_capacity() {
+        let csv = ["7"; 10_000].join(",\n");
+        let mut gids = Vec::new();
+        parse_csv(&csv, &mut gids).unwrap();
+        assert_eq!(vec![Gid(7); 10_000], gids);
+        assert!(gids.capacity() >= gids.len());
+
+        // A buffer that already fits the gids is not reallocated.
+        let mut gids = Vec::with_capacity(10_000);
+        let buffer = gids.as_ptr();
+        parse_csv(&csv, &mut gids).unwrap();
+        assert_eq!(10_000, gids.len());
+        assert_eq!(buffer, gids.as_ptr());
+
+        let mut gids = Vec::new();
+        parse_csv("1,2,3,\n", &mut gids).unwrap();
+        assert_eq!(vec![Gid(1), Gid(2), Gid(3)], gids);
+        assert!(gids.capacity() >= gids.len());
+    }
+
+    #[test]
+    fn test_decode_into() {
+        let csv = "1,2,\n3,4";
+        let base64 = BASE64_STANDARD.encode([5u32, 0, 7, 8].map(u32::to_le_bytes).concat());
+        let mut gids = Vec::new();
+        for (data, encoding) in [(csv, "csv"), (base64.as_str(), "base64")] {
+            let xml = format!(r#"
+                <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                    <layer id="1" name="layer" width="2" height="2">
+                        <data encoding="{encoding}">{data}</data>
+                    </layer>
+                </map>
+            "#);
+            let map = Map::parse_str(&xml).unwrap();
+            let layer = map.layers()[0].as_tile_layer().unwrap();
+            TileLayer::decode_into(data, Some(encoding), None, &mut gids).unwrap();
+            let expected: Vec<Gid> = layer.gids().map(|(_, _, gid)| gid).collect();
+            assert_eq!(expected, gids);
+        }
+        assert_eq!(vec![Gid(5), Gid::NULL, Gid(7), Gid(8)], gids);
+    }
+
+    #[test]
+    fn test_set_gid() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="layer" width="2" height="2">
+                    <data encoding="csv">1,0,0,4</data>
+                </layer>
+            </map>
+        "#;
+        let mut map = Map::parse_str(xml).unwrap();
+        let layer = map.tile_layer_mut(1).unwrap();
+
+        // Inside the region.
+        layer.set_gid(1, 0, Gid(2)).unwrap();
+        layer.set_gid(1, 1, Gid::NULL).unwrap();
+        assert_eq!(TileLayerRegion { x: 0, y: 0, width: 2, height: 2 }, layer.region());
+        assert_eq!(Gid(2), layer.gid_at(1, 0));
+        assert_eq!(2, layer.gids().non_null().count());
+
+        // Outside the region, towards negative coordinates.
+        layer.set_gid(-2, -1, Gid(7)).unwrap();
+        assert_eq!(TileLayerRegion { x: -2, y: -1, width: 4, height: 3 }, layer.region());
+        assert_eq!(Gid(7), layer.gid_at(-2, -1));
+        assert_eq!(Gid(1), layer.gid_at(0, 0));
+        assert_eq!(Gid(2), layer.gid_at(1, 0));
+        assert_eq!(Gid::NULL, layer.gid_at(-1, 0));
+        assert_eq!(3, layer.gids().non_null().count());
+
+        // Outside the region, towards positive coordinates.
+        layer.set_gid(3, 2, Gid(9)).unwrap();
+        assert_eq!(TileLayerRegion { x: -2, y: -1, width: 6, height: 4 }, layer.region());
+        assert_eq!(Gid(9), layer.gid_at(3, 2));
+        assert_eq!(Gid(7), layer.gid_at(-2, -1));
+        assert!(!layer.is_empty());
+
+        assert!(map.tile_layer_mut(2).is_none());
+    }
+
+    #[test]
+    fn test_set_gid_extreme_coordinates() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20" infinite="1">
+                <layer id="1" name="empty" width="2" height="2">
+                    <data encoding="csv"></data>
+                </layer>
+            </map>
+        "#;
+        let mut map = Map::parse_str(xml).unwrap();
+        let layer = map.tile_layer_mut(1).unwrap();
+        layer.set_gid(i32::MAX, i32::MIN, Gid(3)).unwrap();
+        assert_eq!(TileLayerRegion { x: i32::MAX, y: i32::MIN, width: 1, height: 1 }, layer.region());
+        assert_eq!(Gid(3), layer.gid_at(i32::MAX, i32::MIN));
+        assert_eq!(Gid::NULL, layer.gid_at(i32::MIN, i32::MAX));
+
+        // Spanning every coordinate is too large to allocate, and leaves the layer as it was.
+        let result = layer.set_gid(i32::MIN, i32::MAX, Gid(4));
+        assert!(matches!(result, Err(Error::LayerTooLargeError { width: u32::MAX, height: u32::MAX })));
+        assert_eq!(TileLayerRegion { x: i32::MAX, y: i32::MIN, width: 1, height: 1 }, layer.region());
+        assert_eq!(vec![(i32::MAX, i32::MIN, Gid(3))], layer.gids().collect::<Vec<_>>());
+
+        let far = TileLayerRegion { x: i32::MIN, y: i32::MIN, width: 1, height: 1 };
+        let near = TileLayerRegion { x: 10, y: 10, width: 5, height: 5 };
+        assert_eq!(TileLayerRegion { x: i32::MIN, y: i32::MIN, width: 2147483663, height: 2147483663 }, far.union(near));
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_undeclared_compression() {
+        use std::io::Write;
+        use flate2::write::{GzEncoder, ZlibEncoder};
+        use flate2::Compression;
+
+        let bytes = [5u32, 0, 7, 8].map(u32::to_le_bytes).concat();
+        let mut gzip = GzEncoder::new(Vec::new(), Compression::default());
+        gzip.write_all(&bytes).unwrap();
+        let mut zlib = ZlibEncoder::new(Vec::new(), Compression::best());
+        zlib.write_all(&bytes).unwrap();
+
+        let mut gids = Vec::new();
+        for compressed in [gzip.finish().unwrap(), zlib.finish().unwrap()] {
+            let data = BASE64_STANDARD.encode(compressed);
+            TileLayer::decode_into(&data, Some("base64"), None, &mut gids).unwrap();
+            assert_eq!(vec![Gid(5), Gid::NULL, Gid(7), Gid(8)], gids);
+        }
+
+        // Uncompressed gids that happen to start with the gzip magic bytes.
+        let bytes = [0x8B1F_u32, 3].map(u32::to_le_bytes).concat();
+        TileLayer::decode_into(&BASE64_STANDARD.encode(bytes), Some("base64"), None, &mut gids).unwrap();
+        assert_eq!(vec![Gid(0x8B1F), Gid(3)], gids);
+    }
+
+    #[test]
+    fn test_finite_and_infinite_layers_match() {
+        let xml_map = |infinite: u8, data: &str| format!(r#"
+            <map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20" infinite="{infinite}">
+                <layer id="1" name="layer" width="3" height="2">
+                    <data encoding="csv">{data}</data>
+                </layer>
+            </map>
+        "#);
+        let json_map = |infinite: bool, layer: &str| format!(r#"{{
+            "orientation": "orthogonal", "width": 3, "height": 2, "tilewidth": 20, "tileheight": 20, "infinite": {infinite},
+            "layers": [{{ "id": 1, "name": "layer", "type": "tilelayer", "width": 3, "height": 2, {layer} }}]
+        }}"#);
+        let maps = [
+            Map::parse_str(&xml_map(0, "1,0,2,\n0,3,4")).unwrap(),
+            Map::parse_json_str(&json_map(false, r#""data": [1, 0, 2, 0, 3, 4]"#)).unwrap(),
+            Map::parse_str(&xml_map(1, r#"<chunk x="0" y="0" width="3" height="2">1,0,2,0,3,4</chunk>"#)).unwrap(),
+            Map::parse_str(&xml_map(1, r#"
+                <chunk x="0" y="1" width="3" height="1">0,3,4</chunk>
+                <chunk x="0" y="0" width="3" height="1">1,0,2</chunk>
+            "#)).unwrap(),
+            Map::parse_json_str(&json_map(true, r#""chunks": [{ "x": 0, "y": 0, "width": 3, "height": 2, "data": [1, 0, 2, 0, 3, 4] }]"#)).unwrap(),
+        ];
+
+        let finite = maps[0].layers()[0].as_tile_layer().unwrap();
+        assert_eq!(TileLayerRegion { x: 0, y: 0, width: 3, height: 2 }, finite.region());
+        for map in &maps {
+            let layer = map.layers()[0].as_tile_layer().unwrap();
+            assert_eq!(finite.region(), layer.region());
+            assert_eq!(finite.gids().collect::<Vec<_>>(), layer.gids().collect::<Vec<_>>());
+            assert_eq!(
+                finite.gids_ordered(RenderOrder::LeftUp).collect::<Vec<_>>(),
+                layer.gids_ordered(RenderOrder::LeftUp).collect::<Vec<_>>(),
+            );
+            for y in -1..=2 {
+                for x in -1..=3 {
+                    assert_eq!(finite.gid_at(x, y), layer.gid_at(x, y), "gid at {x}, {y}");
+                }
+            }
+        }
+        assert_eq!(Gid(4), finite.gid_at(2, 1));
+        assert_eq!(Gid::NULL, finite.gid_at(-1, 0));
+
+        // Infinite layers without chunks are empty rather than full of null gids.
+        let finite = Map::parse_str(&xml_map(0, "")).unwrap();
+        let infinite = Map::parse_str(&xml_map(1, "")).unwrap();
+        let finite = finite.layers()[0].as_tile_layer().unwrap();
+        let infinite = infinite.layers()[0].as_tile_layer().unwrap();
+        assert!(finite.is_empty() && infinite.is_empty());
+        assert_eq!(TileLayerRegion::default(), infinite.region());
+        assert_eq!(0, infinite.gids().count());
+        assert_eq!(finite.gid_at(1, 1), infinite.gid_at(1, 1));
+    }
+
+    #[test]
+    fn test_width_and_height() {
+        let xml = r#"
+            <map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="layer" width="3" height="2">
+                    <data encoding="csv">0,0,0,0,0,0</data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers()[0].as_tile_layer().unwrap();
+        assert_eq!(3, layer.width());
+        assert_eq!(2, layer.height());
+    }
+
+    #[test]
+    fn test_group_layer_children() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <group id="1" name="group">
+                    <layer id="2" name="tiles" width="1" height="1">
+                        <data encoding="csv">0</data>
+                    </layer>
+                    <imagelayer id="3" name="image">
+                        <image source="images/pepe.png"/>
+                    </imagelayer>
+                    <objectgroup id="4" name="objects">
+                        <object id="1" x="1" y="2"/>
+                    </objectgroup>
+                    <group id="5" name="nested"/>
+                </group>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let group = map.layers()[0].as_group_layer().unwrap();
+        assert_eq!(4, group.layers().len());
+        assert!(group.layers()[0].as_tile_layer().is_some());
+        assert_eq!("images/pepe.png", group.layers()[1].as_image_layer().unwrap().image().source());
+        assert_eq!(1, group.layers()[2].as_object_group_layer().unwrap().objects().len());
+        assert!(group.layers()[3].as_group_layer().is_some());
+    }
+
+    #[test]
+    fn test_parallax_default() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <imagelayer id="1" name="image"/>
+                <imagelayer id="2" name="scrolling" parallaxx="0.5"/>
+            </map>
+        "#;
+        let json = r#"{
+            "orientation": "orthogonal", "width": 1, "height": 1, "tilewidth": 20, "tileheight": 20,
+            "layers": [
+                { "type": "imagelayer", "id": 1, "name": "image", "image": "" },
+                { "type": "imagelayer", "id": 2, "name": "scrolling", "image": "", "parallaxx": 0.5 }
+            ]
+        }"#;
+        for map in [Map::parse_str(xml).unwrap(), Map::parse_json_str(json).unwrap()] {
+            assert_eq!((1.0, 1.0), map.layers()[0].parallax());
+            assert_eq!((0.5, 1.0), map.layers()[1].parallax());
+        }
     }
-    Ok(result)
 }
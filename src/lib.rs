@@ -5,8 +5,10 @@ mod tile;
 mod image;
 mod object;
 mod properties;
+mod wang;
 mod world;
 mod common;
+mod coordinates;
 mod error;
 mod utils;
 
@@ -17,7 +19,9 @@ pub use tile::*;
 pub use image::*;
 pub use object::*;
 pub use properties::*;
+pub use wang::*;
 pub use world::*;
 pub use common::*;
+pub use coordinates::*;
 pub use error::*;
 pub(crate) use utils::*;
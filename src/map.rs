@@ -1,13 +1,18 @@
-use std::io::Read;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
 use std::str::FromStr;
 use roxmltree::{Document, Node};
-use crate::{Color, Error, Gid, Layer, Orientation, Properties, Result, Tileset};
+use serde_json::Value;
+use crate::{collect_objects_mut, find_tile_layer_mut, format_extension, json_array, json_bool, json_f32, json_i32, json_object, json_str, json_u32, parse_bool, read_text, strip_bom, xml_root_end, AllLayers, Color, ComposedLayers, CoordinateSystem, Error, Gid, JsonObject, Layer, LayerKind, Object, Orientation, ParseContext, Properties, Result, Tile, TileLayer, TileLayerRegion, Tileset, Transform};
 
 
 /// A tiled map parsed from a map file.
-#[derive(Debug)]
+#[derive(Default, Debug)]
 pub struct Map {
     version: String,
+    tiled_version: String,
     class: String,
     orientation: Orientation,
     render_order: RenderOrder,
@@ -20,90 +25,474 @@ pub struct Map {
     stagger_index: Option<StaggerIndex>,
     parallax_origin_x: f32,
     parallax_origin_y: f32,
-    background_color: Color,
+    background_color: Option<Color>,
     tileset_entries: Vec<TilesetEntry>,
     infinite: bool,
+    compression_level: Option<i32>,
+    next_layer_id: u32,
+    next_object_id: u32,
     layers: Vec<Layer>,
     properties: Properties,
 }
 
-impl Default for Map {
-    fn default() -> Self {
-        Self {
-            version: Default::default(),
-            class: Default::default(),
-            orientation: Default::default(),
-            render_order: Default::default(),
-            width: Default::default(),
-            height: Default::default(),
-            tile_width: Default::default(),
-            tile_height: Default::default(),
-            hex_side_length: Default::default(),
-            stagger_axis: Default::default(),
-            stagger_index: Default::default(),
-            parallax_origin_x: Default::default(),
-            parallax_origin_y: Default::default(),
-            background_color: Color::TRANSPARENT,
-            tileset_entries: Default::default(),
-            infinite: Default::default(),
-            layers: Default::default(),
-            properties: Default::default(),
-        }
-    }
-}
-
 impl Map {
     pub fn version(&self) -> &str { &self.version }
+
+    /// Major and minor components of [`version`](Self::version).
+    /// None if the version is missing or malformed.
+    pub fn version_tuple(&self) -> Option<(u32, u32)> {
+        let mut parts = self.version.split('.');
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some((major, minor))
+    }
+
+    /// Version of Tiled that saved the map, like "1.10.2".
+    pub fn tiled_version(&self) -> &str { &self.tiled_version }
+
     pub fn class(&self) -> &str { &self.class }
     pub fn orientation(&self) -> Orientation { self.orientation }
     pub fn render_order(&self) -> RenderOrder { self.render_order }
+
+    /// Render order that applies to the map.
+    /// None for non-orthogonal maps, since Tiled ignores the render order of other orientations.
+    pub fn effective_render_order(&self) -> Option<RenderOrder> {
+        match self.orientation {
+            Orientation::Orthogonal => Some(self.render_order),
+            _ => None,
+        }
+    }
     pub fn width(&self) -> u32 { self.width }
     pub fn height(&self) -> u32 { self.height }
     pub fn tile_width(&self) -> u32 { self.tile_width }
     pub fn tile_height(&self) -> u32 { self.tile_height }
+    pub fn tile_size(&self) -> (u32, u32) { (self.tile_width, self.tile_height) }
     pub fn hex_side_length(&self) -> Option<i32> { self.hex_side_length }
     pub fn stagger_axis(&self) -> Option<StaggerAxis> { self.stagger_axis }
     pub fn stagger_index(&self) -> Option<StaggerIndex> { self.stagger_index }
     pub fn parallax_origin_x(&self) -> f32 { self.parallax_origin_x }
     pub fn parallax_origin_y(&self) -> f32 { self.parallax_origin_y }
-    pub fn background_color(&self) -> Color { self.background_color }
+
+    /// Color the map is cleared with before rendering.
+    /// None when the map does not specify one, in which case nothing should be cleared.
+    pub fn background_color(&self) -> Option<Color> { self.background_color }
+
     pub fn tileset_entries(&self) -> &[TilesetEntry] { &self.tileset_entries }
     pub fn infinite(&self) -> bool { self.infinite }
+
+    /// Compression level Tiled uses when saving compressed layer data.
+    /// -1 means the algorithm's default. None if the map does not specify one.
+    pub fn compression_level(&self) -> Option<i32> { self.compression_level }
+
+    /// Id the next layer added to the map will get.
+    pub fn next_layer_id(&self) -> u32 { self.next_layer_id }
+
+    /// Id the next object added to the map will get.
+    pub fn next_object_id(&self) -> u32 { self.next_object_id }
+
     pub fn layers(&self) -> &[Layer] { &self.layers }
     pub fn properties(&self) -> &Properties{ &self.properties }
 
+    /// Iterates over all layers in the map, including those nested in groups.
+    /// Layers are visited depth-first, with groups preceding their children.
+    pub fn all_layers(&self) -> AllLayers<'_> {
+        AllLayers::new(&self.layers)
+    }
+
+    /// Finds a tile layer by id for editing, searching nested groups as well.
+    pub fn tile_layer_mut(&mut self, id: u32) -> Option<&mut TileLayer> {
+        find_tile_layer_mut(&mut self.layers, id)
+    }
+
+    /// Iterates mutably over all objects in object group layers, including those nested in groups.
+    /// Layers themselves are not exposed mutably. Tiles are edited via [`tile_layer_mut`](Self::tile_layer_mut).
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut Object> {
+        let mut objects = Vec::new();
+        collect_objects_mut(&mut self.layers, &mut objects);
+        objects.into_iter()
+    }
+
+    /// Compares the map to another version of it, such as after the file was saved again.
+    /// Layers are matched by id, including those nested in groups.
+    pub fn diff(&self, other: &Map) -> MapDiff {
+        let mut result = MapDiff::default();
+        for layer in self.all_layers() {
+            let Some(other_layer) = other.layer(layer.id()) else {
+                result.changed_layers.insert(layer.id());
+                continue;
+            };
+            if layer != other_layer {
+                result.changed_layers.insert(layer.id());
+            }
+            if let (Some(tile_layer), Some(other_tile_layer)) = (layer.as_tile_layer(), other_layer.as_tile_layer()) {
+                let region = tile_layer.region().union(other_tile_layer.region());
+                // Computed in i64, since a region may end at the edge of the i32 range.
+                let rows = i64::from(region.y)..i64::from(region.y) + i64::from(region.height);
+                let columns = i64::from(region.x)..i64::from(region.x) + i64::from(region.width);
+                let changed_tiles: BTreeSet<(i32, i32)> = rows
+                    .flat_map(|y| columns.clone().map(move |x| (x as i32, y as i32)))
+                    .filter(|&(x, y)| tile_layer.gid_at(x, y) != other_tile_layer.gid_at(x, y))
+                    .collect();
+                if !changed_tiles.is_empty() {
+                    result.changed_tiles.insert(layer.id(), changed_tiles);
+                }
+            }
+        }
+        for other_layer in other.all_layers() {
+            if self.layer(other_layer.id()).is_none() {
+                result.changed_layers.insert(other_layer.id());
+            }
+        }
+        result
+    }
+
+    /// Every distinct non-empty object class in the map's object layers, including those nested in groups.
+    /// Objects of tiles in tilesets are not included.
+    pub fn object_types(&self) -> BTreeSet<String> {
+        self.all_layers()
+            .filter_map(|layer| layer.as_object_group_layer())
+            .flat_map(|object_group| object_group.objects())
+            .map(|object| object.typ())
+            .filter(|typ| !typ.is_empty())
+            .map(String::from)
+            .collect()
+    }
+
+    /// Number of times each gid is used across all tile layers, including those nested in groups.
+    /// Flip flags are stripped, so flipped and rotated uses count towards the same gid. Null gids are not counted.
+    pub fn gid_histogram(&self) -> HashMap<Gid, usize> {
+        let mut histogram = HashMap::new();
+        let gids = self.all_layers()
+            .filter_map(|layer| layer.as_tile_layer())
+            .flat_map(|tile_layer| tile_layer.gids())
+            .map(|(_, _, gid)| Gid(gid.value()))
+            .filter(|&gid| gid != Gid::NULL);
+        for gid in gids {
+            *histogram.entry(gid).or_insert(0) += 1;
+        }
+        histogram
+    }
+
+    /// Union of the regions of all tile layers, in tiles.
+    /// For infinite maps, this is the area actually occupied by chunks.
+    pub fn bounds(&self) -> TileLayerRegion {
+        self.all_layers()
+            .filter_map(|layer| layer.as_tile_layer())
+            .map(|tile_layer| tile_layer.region())
+            .fold(TileLayerRegion::default(), TileLayerRegion::union)
+    }
+
+    /// Converts between tile and pixel coordinates according to the map's orientation.
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        CoordinateSystem::new(self)
+    }
+
+    /// All attributes that determine how tiles are projected and drawn, in one place.
+    pub fn projection(&self) -> MapProjection {
+        MapProjection {
+            orientation: self.orientation,
+            tile_width: self.tile_width,
+            tile_height: self.tile_height,
+            stagger_axis: self.stagger_axis,
+            stagger_index: self.stagger_index,
+            hex_side_length: self.hex_side_length,
+            render_order: self.render_order,
+        }
+    }
+
+    /// Finds a layer by id, searching nested groups as well.
+    pub fn layer(&self, id: u32) -> Option<&Layer> {
+        self.all_layers().find(|layer| layer.id() == id)
+    }
+
+    /// Iterates over all layers in the map like [`all_layers`](Self::all_layers),
+    /// along with the opacity, tint, offset, parallax and visibility they inherit from the groups containing them.
+    pub fn composed_layers(&self) -> ComposedLayers<'_> {
+        ComposedLayers::new(&self.layers)
+    }
+
+    /// Tint color of a layer, multiplied by the tint colors of all groups containing it.
+    /// None if no layer has the given id.
+    pub fn effective_tint(&self, layer_id: u32) -> Option<Color> {
+        self.composed_layers()
+            .find(|composed| composed.layer.id() == layer_id)
+            .map(|composed| composed.tint_color)
+    }
+
+    /// True if a layer and all groups containing it are visible, which is what determines whether it is rendered.
+    /// None if no layer has the given id.
+    pub fn is_layer_visible(&self, layer_id: u32) -> Option<bool> {
+        self.composed_layers()
+            .find(|composed| composed.layer.id() == layer_id)
+            .map(|composed| composed.visible)
+    }
+
+    /// Gets the [`Tile`] a gid refers to.
+    /// None if the gid is null, out of range, or refers to an unresolved external tileset.
+    pub fn tile_for_gid(&self, gid: Gid) -> Option<&Tile> {
+        if gid == Gid::NULL {
+            return None;
+        }
+        let (tileset_idx, tile_id) = self.tile_location_of(gid)?;
+        match &self.tileset_entries[tileset_idx].kind {
+            TilesetEntryKind::Internal(tileset) => tileset.tile(tile_id),
+            TilesetEntryKind::External(_) => None,
+        }
+    }
+
+    /// True if any tile layer has a non-null gid at the given coordinates, in tiles.
+    pub fn any_tile_at(&self, x: i32, y: i32) -> bool {
+        self.first_gid_at(x, y).is_some()
+    }
+
+    /// Topmost non-null gid at the given coordinates (in tiles) across all tile layers, including those nested in groups.
+    /// None if every tile layer is empty there.
+    pub fn first_gid_at(&self, x: i32, y: i32) -> Option<Gid> {
+        self.all_layers()
+            .filter_map(|layer| layer.as_tile_layer())
+            .map(|tile_layer| tile_layer.gid_at(x, y))
+            .filter(|&gid| gid != Gid::NULL)
+            .last()
+    }
+
+    /// Iterates over the non-null tiles of a tile layer, along with their x, y coordinates (in tiles) and flip/rotation.
+    /// Tiles that cannot be resolved via [`tile_for_gid`](Self::tile_for_gid) are skipped.
+    /// Empty if no tile layer has the given id.
+    pub fn layer_tiles(&self, layer_id: u32) -> impl Iterator<Item = (i32, i32, &Tile, Transform)> + '_ {
+        self.layer(layer_id)
+            .and_then(|layer| layer.as_tile_layer())
+            .into_iter()
+            .flat_map(|tile_layer| tile_layer.gids().non_null())
+            .filter_map(|(x, y, gid)| Some((x, y, self.tile_for_gid(gid)?, gid.transform())))
+    }
+
+    /// Iterates over every tile to draw, in paint order.
+    /// Tile layers are visited bottom to top, including those nested in groups, and their tiles in the map's
+    /// [`effective_render_order`](Self::effective_render_order), or right-down for non-orthogonal maps.
+    /// Hidden layers, including those in hidden groups, are skipped, as are tiles that cannot be resolved via [`tile_for_gid`](Self::tile_for_gid).
+    pub fn draw_list(&self) -> impl Iterator<Item = DrawItem<'_>> + '_ {
+        let render_order = self.effective_render_order().unwrap_or_default();
+        let mut tile_layers = Vec::new();
+        Self::visible_tile_layers_in(&self.layers, &mut tile_layers);
+        tile_layers.into_iter().flat_map(move |(layer, tile_layer)| {
+            tile_layer
+                .gids_ordered(render_order)
+                .filter_map(move |(x, y, gid)| Some(DrawItem {
+                    layer,
+                    x,
+                    y,
+                    tile: self.tile_for_gid(gid)?,
+                    transform: gid.transform(),
+                }))
+        })
+    }
+
+    fn visible_tile_layers_in<'a>(layers: &'a [Layer], result: &mut Vec<(&'a Layer, &'a TileLayer)>) {
+        for layer in layers.iter().filter(|layer| layer.visible()) {
+            match layer.kind() {
+                LayerKind::TileLayer(tile_layer) => result.push((layer, tile_layer)),
+                LayerKind::GroupLayer(group_layer) => Self::visible_tile_layers_in(group_layer.layers(), result),
+                _ => {}
+            }
+        }
+    }
+
+    /// Every image source referenced by the map, without duplicates.
+    /// Includes tileset images, images of tiles in collection tilesets, and image layer images.
+    /// Images of unresolved external tilesets are not included.
+    /// Sources are returned as written, so those of external tilesets are relative to the tileset file.
+    pub fn image_sources(&self) -> Vec<&str> {
+        let mut images = Vec::new();
+        for entry in &self.tileset_entries {
+            let TilesetEntryKind::Internal(tileset) = &entry.kind else { continue };
+            images.extend(tileset.image());
+            let mut tiles: Vec<_> = tileset.tiles().collect();
+            tiles.sort_by_key(|(id, _)| *id);
+            images.extend(tiles.into_iter().filter_map(|(_, tile)| tile.image()));
+        }
+        images.extend(self.all_layers()
+            .filter_map(|layer| layer.as_image_layer())
+            .map(|image_layer| image_layer.image())
+        );
+        let mut sources = Vec::new();
+        for source in images.into_iter().map(|image| image.source()) {
+            if !source.is_empty() && !sources.contains(&source) {
+                sources.push(source);
+            }
+        }
+        sources
+    }
+
+    /// Union of [`bounds`](Self::bounds) and the extents of all object shapes, in pixels.
+    /// Returned as (x, y, width, height).
+    pub fn pixel_bounds(&self) -> (f32, f32, f32, f32) {
+        let mut min_x = f32::MAX;
+        let mut min_y = f32::MAX;
+        let mut max_x = f32::MIN;
+        let mut max_y = f32::MIN;
+        let bounds = self.bounds();
+        if !bounds.is_empty() {
+            min_x = (bounds.x * self.tile_width as i32) as f32;
+            min_y = (bounds.y * self.tile_height as i32) as f32;
+            max_x = min_x + (bounds.width * self.tile_width) as f32;
+            max_y = min_y + (bounds.height * self.tile_height) as f32;
+        }
+        let objects = self.all_layers()
+            .filter_map(|layer| layer.as_object_group_layer())
+            .flat_map(|object_group| object_group.objects());
+        for object in objects {
+            let (object_min_x, object_min_y, object_max_x, object_max_y) = object.bounds();
+            min_x = min_x.min(object_min_x);
+            min_y = min_y.min(object_min_y);
+            max_x = max_x.max(object_max_x);
+            max_y = max_y.max(object_max_y);
+        }
+        if min_x > max_x || min_y > max_y {
+            return (0.0, 0.0, 0.0, 0.0);
+        }
+        (min_x, min_y, max_x - min_x, max_y - min_y)
+    }
+
+    /// Checks that tileset first gids are nonzero and strictly increasing, which tile lookups depend on.
+    /// Tiled always saves them this way, but hand-edited maps may not.
+    pub fn validate_tilesets(&self) -> Result<()> {
+        let mut previous = Gid::NULL.0;
+        for entry in &self.tileset_entries {
+            if entry.first_gid <= previous {
+                return Err(Error::InvalidTilesetOrderError { previous, next: entry.first_gid });
+            }
+            previous = entry.first_gid;
+        }
+        Ok(())
+    }
+
     /// Tileset index and local tile id of a [`Tile`](crate::Tile).
+    /// The tileset is the one with the largest first gid not above the gid, even if entries are out of order.
     pub fn tile_location_of(&self, gid: Gid) -> Option<(usize, u32)> {
         let gid = gid.value();
-        for (tileset_idx, tileset) in self.tileset_entries.iter().enumerate().rev() {
-            if gid >= tileset.first_gid {
-                let tile_id = gid - tileset.first_gid;
-                return Some((tileset_idx, tile_id));
+        let (tileset_idx, entry) = self.tileset_entries
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.first_gid <= gid)
+            .max_by_key(|(_, entry)| entry.first_gid)?;
+        Some((tileset_idx, gid - entry.first_gid))
+    }
+
+    /// Tileset entry whose range of gids covers a gid, as found by [`tile_location_of`](Self::tile_location_of).
+    /// Unlike [`tile_for_gid`](Self::tile_for_gid), the tile is not looked up, so unresolved external tilesets are returned as well.
+    /// None if the gid is below every first gid, like the null gid.
+    pub fn tileset_entry_for_gid(&self, gid: Gid) -> Option<&TilesetEntry> {
+        let (tileset_idx, _) = self.tile_location_of(gid)?;
+        Some(&self.tileset_entries[tileset_idx])
+    }
+
+    /// Loads a map from a file, along with any external tilesets it references.
+    /// Files ending in `.tmx` are parsed as XML, and files ending in `.tmj` or `.json` as JSON.
+    /// With the `flate2` feature, these may be followed by `.gz` for gzipped files.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        let mut map = match format_extension(path) {
+            Some("tmx") => Self::parse(file)?,
+            Some("tmj") | Some("json") => Self::parse_json(file)?,
+            _ => return Err(Error::UnsupportedFileError),
+        };
+        let dir = path.parent().unwrap_or(Path::new(""));
+        map.resolve_tilesets(dir)?;
+        Ok(map)
+    }
+
+    /// Loads external tilesets relative to the directory of the map file, and embeds them in their entries.
+    /// The original path remains available via [`TilesetEntry::source`].
+    pub fn resolve_tilesets(&mut self, dir: impl AsRef<Path>) -> Result<()> {
+        let dir = dir.as_ref();
+        for entry in &mut self.tileset_entries {
+            if let TilesetEntryKind::External(source) = &entry.kind {
+                let tileset = Tileset::load(dir.join(source))?;
+                entry.kind = TilesetEntryKind::Internal(tileset);
             }
         }
-        None
+        Ok(())
     }
 
-    pub fn parse(mut read: impl Read) -> Result<Self> {
-        let mut xml_str = String::new();
-        read.read_to_string(&mut xml_str)?;
+    /// Parses XML that is either UTF-8, or UTF-16 starting with a byte order mark.
+    /// With the `flate2` feature, gzipped files such as `.tmx.gz` are decompressed first.
+    pub fn parse(read: impl Read) -> Result<Self> {
+        let xml_str = read_text(read)?;
         Self::parse_str(&xml_str)
     }
 
+    /// Parses a map from UTF-8 encoded XML, without copying it.
+    pub fn parse_bytes(xml_bytes: &[u8]) -> Result<Self> {
+        let xml_str = std::str::from_utf8(xml_bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+        Self::parse_str(xml_str)
+    }
+
+    /// Parses a map from an XML file by memory-mapping it, avoiding a copy of its contents.
+    /// Unlike [`load`](Self::load), external tilesets are not resolved.
+    #[cfg(feature = "mmap")]
+    pub fn parse_mmap(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: The mapping is only read while parsing, and the file is assumed not to be modified meanwhile.
+        let mmap = unsafe { memmap2::Mmap::map(&file)? };
+        Self::parse_bytes(&mmap)
+    }
+
+    /// Parses a map from the start of some bytes, which may be followed by unrelated data.
+    /// Also returns the number of bytes used, up to and including the end of the root element.
+    pub fn parse_prefix(bytes: &[u8]) -> Result<(Self, usize)> {
+        let end = xml_root_end(bytes).ok_or(roxmltree::Error::UnexpectedEndOfStream)?;
+        Ok((Self::parse_bytes(&bytes[..end])?, end))
+    }
+
+    pub fn parse_json(read: impl Read) -> Result<Self> {
+        let json_str = read_text(read)?;
+        Self::parse_json_str(&json_str)
+    }
+
+    pub fn parse_json_str(json_str: &str) -> Result<Self> {
+        let mut map = Self::default();
+        let map_value: Value = serde_json::from_str(strip_bom(json_str))?;
+        map.parse_json_object(json_object(&map_value)?)?;
+        Ok(map)
+    }
+
+    /// Writes the map as Tiled-compatible JSON, the format of `.tmj` files.
+    /// Tile layer data is written as unencoded arrays of gids, and external tilesets keep referencing their source.
+    pub fn write_json(&self, w: impl Write) -> Result<()> {
+        serde_json::to_writer_pretty(w, &self.to_json())?;
+        Ok(())
+    }
+
     pub fn parse_str(xml_str: &str) -> Result<Self> {
         let mut map = Self::default();
-        let map_doc = Document::parse(xml_str)?;
-        let root = map_doc.root();
-        for node in root.children() {
-            let tag_name = node.tag_name().name();
-            match tag_name {
-                "map" => map.parse_node(node)?,
-                _ => {},
-            }
+        let map_doc = Document::parse(strip_bom(xml_str))?;
+        let root = map_doc.root_element();
+        if root.tag_name().name() != "map" {
+            return Err(Error::WrongRootElementError { found: root.tag_name().name().into() });
         }
+        map.parse_node(root)?;
         Ok(map)
     }
 
+    /// Attributes layers depend on, which must be parsed before any layer.
+    fn parse_context(&self) -> ParseContext {
+        ParseContext {
+            infinite: self.infinite,
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    /// Adds a parsed tileset entry, rejecting it if another entry has the same first gid.
+    fn push_tileset_entry(&mut self, entry: TilesetEntry) -> Result<()> {
+        if self.tileset_entries.iter().any(|existing| existing.first_gid == entry.first_gid) {
+            return Err(Error::DuplicateFirstGidError(entry.first_gid));
+        }
+        self.tileset_entries.push(entry);
+        Ok(())
+    }
+
     /// Parses inner map element as a [`Map`].
     fn parse_node(&mut self, map_node: Node) -> Result<()> {
 
@@ -113,9 +502,10 @@ impl Map {
             let value = attribute.value();
             match name {
                 "version" => self.version = value.into(),
-                "class" => self.class = value.into(),
+                "tiledversion" => self.tiled_version = value.into(),
+                "class" | "type" => self.class = value.into(),
                 "orientation" => self.orientation = Orientation::parse(value)?,
-                "renderorder" => self.render_order = RenderOrder::from_str(value)?,
+                "renderorder" => self.render_order = RenderOrder::parse(value)?,
                 "width" => self.width = value.parse()?,
                 "height" => self.height = value.parse()?,
                 "tilewidth" => self.tile_width = value.parse()?,
@@ -125,12 +515,11 @@ impl Map {
                 "staggerindex" => self.stagger_index = Some(value.parse()?),
                 "parallaxoriginx" => self.parallax_origin_x = value.parse()?,
                 "parallaxoriginy" => self.parallax_origin_y = value.parse()?,
-                "backgroundcolor" => self.background_color = value.parse()?,
-                "infinite" => self.infinite = match value {
-                    "0" => false,
-                    "1" => true,
-                    _ => return Err(Error::ParsingError),
-                },
+                "backgroundcolor" => self.background_color = Some(value.parse()?),
+                "infinite" => self.infinite = parse_bool(value)?,
+                "compressionlevel" => self.compression_level = Some(value.parse()?),
+                "nextlayerid" => self.next_layer_id = value.parse()?,
+                "nextobjectid" => self.next_object_id = value.parse()?,
                 _ => {}
             }
         }
@@ -138,16 +527,16 @@ impl Map {
         // Children
         for node in map_node.children() {
             match node.tag_name().name() {
-                "tileset" => self.tileset_entries.push(TilesetEntry::parse(node)?),
-                "properties" => self.properties = Properties::parse(node)?,
+                "tileset" => self.push_tileset_entry(TilesetEntry::parse(node)?)?,
+                "properties" => self.properties.merge(Properties::parse(node)?),
                 // Note: According to spec, <tileset> elements always appear before <layer>, and <group> elements,
                 // So the tilesets passed in are already complete.
                 "layer" => {
-                    let layer = Layer::parse_tile_layer(node, self.infinite)?;
+                    let layer = Layer::parse_tile_layer(node, self.parse_context())?;
                     self.layers.push(layer);
                 },
                 "group" => {
-                    let layer = Layer::parse_group_layer(node, self.infinite)?;
+                    let layer = Layer::parse_group_layer(node, self.parse_context())?;
                     self.layers.push(layer);
                 },
                 "imagelayer" => {
@@ -164,6 +553,94 @@ impl Map {
 
         Ok(())
     }
+
+    /// Parses a JSON map object as a [`Map`].
+    fn parse_json_object(&mut self, map_object: &JsonObject) -> Result<()> {
+
+        // Attributes
+        for (name, value) in map_object {
+            match name.as_str() {
+                "version" => self.version = json_str(value)?.into(),
+                "tiledversion" => self.tiled_version = json_str(value)?.into(),
+                "class" => self.class = json_str(value)?.into(),
+                "orientation" => self.orientation = Orientation::parse(json_str(value)?)?,
+                "renderorder" => self.render_order = RenderOrder::parse(json_str(value)?)?,
+                "width" => self.width = json_u32(value)?,
+                "height" => self.height = json_u32(value)?,
+                "tilewidth" => self.tile_width = json_u32(value)?,
+                "tileheight" => self.tile_height = json_u32(value)?,
+                "hexsidelength" => self.hex_side_length = Some(json_i32(value)?),
+                "staggeraxis" => self.stagger_axis = Some(json_str(value)?.parse()?),
+                "staggerindex" => self.stagger_index = Some(json_str(value)?.parse()?),
+                "parallaxoriginx" => self.parallax_origin_x = json_f32(value)?,
+                "parallaxoriginy" => self.parallax_origin_y = json_f32(value)?,
+                "backgroundcolor" => self.background_color = Some(json_str(value)?.parse()?),
+                "infinite" => self.infinite = json_bool(value)?,
+                "compressionlevel" => self.compression_level = Some(json_i32(value)?),
+                "nextlayerid" => self.next_layer_id = json_u32(value)?,
+                "nextobjectid" => self.next_object_id = json_u32(value)?,
+                _ => {}
+            }
+        }
+
+        // Children. Layers are parsed last since they depend on "infinite", "width" and "height".
+        if let Some(tilesets) = map_object.get("tilesets") {
+            for tileset_value in json_array(tilesets)? {
+                self.push_tileset_entry(TilesetEntry::parse_json(tileset_value)?)?;
+            }
+        }
+        if let Some(properties) = map_object.get("properties") {
+            self.properties = Properties::parse_json(properties)?;
+        }
+        if let Some(layers) = map_object.get("layers") {
+            for layer_value in json_array(layers)? {
+                self.layers.push(Layer::parse_json(layer_value, self.parse_context())?);
+            }
+        }
+        Ok(())
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("type".into(), "map".into());
+        object.insert("version".into(), self.version.as_str().into());
+        object.insert("tiledversion".into(), self.tiled_version.as_str().into());
+        if !self.class.is_empty() {
+            object.insert("class".into(), self.class.as_str().into());
+        }
+        object.insert("orientation".into(), self.orientation.as_str().into());
+        object.insert("renderorder".into(), self.render_order.as_str().into());
+        object.insert("width".into(), self.width.into());
+        object.insert("height".into(), self.height.into());
+        object.insert("tilewidth".into(), self.tile_width.into());
+        object.insert("tileheight".into(), self.tile_height.into());
+        if let Some(hex_side_length) = self.hex_side_length {
+            object.insert("hexsidelength".into(), hex_side_length.into());
+        }
+        if let Some(stagger_axis) = self.stagger_axis {
+            object.insert("staggeraxis".into(), stagger_axis.as_str().into());
+        }
+        if let Some(stagger_index) = self.stagger_index {
+            object.insert("staggerindex".into(), stagger_index.as_str().into());
+        }
+        object.insert("parallaxoriginx".into(), self.parallax_origin_x.into());
+        object.insert("parallaxoriginy".into(), self.parallax_origin_y.into());
+        if let Some(background_color) = self.background_color {
+            object.insert("backgroundcolor".into(), background_color.to_string().into());
+        }
+        object.insert("infinite".into(), self.infinite.into());
+        if let Some(compression_level) = self.compression_level {
+            object.insert("compressionlevel".into(), compression_level.into());
+        }
+        object.insert("nextlayerid".into(), self.next_layer_id.into());
+        object.insert("nextobjectid".into(), self.next_object_id.into());
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        object.insert("tilesets".into(), self.tileset_entries.iter().map(TilesetEntry::to_json).collect());
+        object.insert("layers".into(), self.layers.iter().map(Layer::to_json).collect());
+        Value::Object(object)
+    }
 }
 
 /// A single tileset stored in a [`Map`].
@@ -171,6 +648,7 @@ impl Map {
 #[derive(Clone, Debug)]
 pub struct TilesetEntry {
     first_gid: u32,
+    source: Option<String>,
     kind: TilesetEntryKind,
 }
 
@@ -179,6 +657,28 @@ impl TilesetEntry {
     pub fn first_gid(&self) -> u32 { self.first_gid }
     pub fn kind(&self) -> &TilesetEntryKind { &self.kind }
 
+    /// Path of the external tileset file, relative to the map.
+    /// Still set after the tileset has been resolved with [`Map::resolve_tilesets`].
+    pub fn source(&self) -> Option<&str> { self.source.as_deref() }
+
+    /// Local id of a gid's tile within this tileset.
+    /// None if the gid is below the first gid, or beyond the tileset's tiles when it has been resolved.
+    pub fn local_id(&self, gid: Gid) -> Option<u32> {
+        let local_id = gid.value().checked_sub(self.first_gid)?;
+        if let TilesetEntryKind::Internal(tileset) = &self.kind {
+            if local_id >= tileset.tile_count() && tileset.tile(local_id).is_none() {
+                return None;
+            }
+        }
+        Some(local_id)
+    }
+
+    /// True if a gid refers to a tile in this tileset.
+    /// See [`local_id`](Self::local_id).
+    pub fn contains(&self, gid: Gid) -> bool {
+        self.local_id(gid).is_some()
+    }
+
     fn parse(entry_node: Node) -> Result<Self> {
         let mut first_gid: u32 = 0;
         let mut source: Option<String> = None;
@@ -199,9 +699,48 @@ impl TilesetEntry {
         }
     }
 
+    fn parse_json(entry_value: &Value) -> Result<Self> {
+        let entry_object = json_object(entry_value)?;
+        let mut first_gid: u32 = 0;
+        let mut source: Option<String> = None;
+        for (name, value) in entry_object {
+            match name.as_str() {
+                "firstgid" => first_gid = json_u32(value)?,
+                "source" => source = Some(json_str(value)?.into()),
+                _ => {}
+            }
+        }
+        if let Some(source) = source {
+            Ok(TilesetEntry::external(first_gid, source))
+        }
+        else {
+            let mut tileset = Tileset::default();
+            tileset.parse_json_object(entry_object)?;
+            Ok(TilesetEntry::internal(first_gid, tileset))
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("firstgid".into(), self.first_gid.into());
+        match &self.source {
+            // Resolved tilesets are still referenced rather than embedded, like in the original file.
+            Some(source) => {
+                object.insert("source".into(), source.as_str().into());
+            },
+            None => {
+                if let TilesetEntryKind::Internal(tileset) = &self.kind {
+                    tileset.write_json(&mut object);
+                }
+            },
+        }
+        Value::Object(object)
+    }
+
     fn internal(first_gid: u32, tileset: Tileset) -> Self {
         Self {            
             first_gid,
+            source: None,
             kind: TilesetEntryKind::Internal(tileset),
         }
     }
@@ -209,12 +748,60 @@ impl TilesetEntry {
     fn external(first_gid: u32, source: String) -> Self {
         Self {            
             first_gid,
+            source: Some(source.clone()),
             kind: TilesetEntryKind::External(source),
         }
     }
 }
 
+/// Differences between two versions of a [`Map`], as returned by [`Map::diff`].
+#[derive(Clone, Eq, PartialEq, Default, Debug)]
+pub struct MapDiff {
+    /// Ids of layers that were added, removed, or differ in any way.
+    /// Groups count as changed when any of their children do.
+    pub changed_layers: BTreeSet<u32>,
+    /// Coordinates (in tiles) of cells whose gid differs, for each tile layer present in both versions.
+    /// Layers without changed cells are omitted.
+    pub changed_tiles: BTreeMap<u32, BTreeSet<(i32, i32)>>,
+}
+
+impl MapDiff {
+    /// True if both versions have identical layers.
+    /// Map attributes, tilesets and properties are not compared.
+    pub fn is_empty(&self) -> bool {
+        self.changed_layers.is_empty()
+    }
+}
+
+/// Attributes of a [`Map`] that determine how its tiles are projected, as returned by [`Map::projection`].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MapProjection {
+    pub orientation: Orientation,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    /// Only set for staggered and hexagonal maps.
+    pub stagger_axis: Option<StaggerAxis>,
+    /// Only set for staggered and hexagonal maps.
+    pub stagger_index: Option<StaggerIndex>,
+    /// Length of the side of a hexagonal tile along the staggered axis, in pixels.
+    /// Only set for hexagonal maps.
+    pub hex_side_length: Option<i32>,
+    /// Only meaningful for orthogonal maps. See [`Map::effective_render_order`].
+    pub render_order: RenderOrder,
+}
+
+/// A tile to draw, as produced by [`Map::draw_list`].
+#[derive(Copy, Clone, Debug)]
+pub struct DrawItem<'a> {
+    pub layer: &'a Layer,
+    pub x: i32,
+    pub y: i32,
+    pub tile: &'a Tile,
+    pub transform: Transform,
+}
+
 /// Either embeds a tileset, or references an external one.
+#[allow(clippy::large_enum_variant)]
 #[derive(Clone, Debug)]
 pub enum TilesetEntryKind {
     Internal(Tileset),
@@ -231,8 +818,9 @@ pub enum RenderOrder {
     LeftUp,
 }
 
-impl RenderOrder {
-    pub fn from_str(value: &str) -> Result<Self> {
+impl FromStr for RenderOrder {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
         match value {
             "right-down" => Ok(Self::RightDown),
             "right-up" => Ok(Self::RightUp),
@@ -243,14 +831,27 @@ impl RenderOrder {
     }
 }
 
+impl RenderOrder {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::RightDown => "right-down",
+            Self::RightUp => "right-up",
+            Self::LeftDown => "left-down",
+            Self::LeftUp => "left-up",
+        }
+    }
+}
+
 /// For staggered and hexagonal maps, determines which axis (X or Y) is staggered.
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
 pub enum StaggerAxis {
     X,
     #[default]
     Y,
-    LeftDown,
-    LeftUp,
 }
 
 impl FromStr for StaggerAxis {
@@ -264,6 +865,19 @@ impl FromStr for StaggerAxis {
     }
 }
 
+impl StaggerAxis {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::X => "x",
+            Self::Y => "y",
+        }
+    }
+}
+
 /// For staggered and hexagonal maps, determines whether the Even or Odd indexes along the staggered axis are shifted.
 #[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
 pub enum StaggerIndex {
@@ -283,10 +897,781 @@ impl FromStr for StaggerIndex {
     }
 }
 
+impl StaggerIndex {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Even => "even",
+            Self::Odd => "odd",
+        }
+    }
+}
+
 
 #[cfg(test)]
 mod test {
-    use crate::{ Gid, Map};
+    use std::collections::{BTreeMap, BTreeSet};
+    use crate::{ Color, Error, FillMode, Gid, Map, MapProjection, ObjectKind, Orientation, RenderOrder, StaggerAxis, StaggerIndex, TileLayer, TileLayerRegion, TileRenderSize, TilesetEntryKind, Transform};
+
+    #[test]
+    fn test_validate_tilesets() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        assert!(map.validate_tilesets().is_ok());
+
+        let xml = xml.replace("firstgid=\"161\"", "firstgid=\"1500\"");
+        let map = Map::parse_str(&xml).unwrap();
+        let error = map.validate_tilesets().unwrap_err();
+        assert!(matches!(error, Error::InvalidTilesetOrderError { previous: 1500, next: 1185 }));
+
+        let xml = xml.replace("firstgid=\"1\"", "firstgid=\"0\"");
+        let map = Map::parse_str(&xml).unwrap();
+        let error = map.validate_tilesets().unwrap_err();
+        assert!(matches!(error, Error::InvalidTilesetOrderError { previous: 0, next: 0 }));
+    }
+
+    #[test]
+    fn test_layer_tiles() {
+        let map = Map::load(concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/finite.tmx")).unwrap();
+        let below = map.layer(1).unwrap();
+        assert_eq!("below", below.name());
+        let tiles: Vec<_> = map.layer_tiles(1).collect();
+        assert_eq!(100, tiles.len());
+
+        let (x, y, tile, transform) = tiles[0];
+        assert_eq!((0, 0), (x, y));
+        assert_eq!(0, tile.id());
+        assert!(tile.properties().contains("is_steve"));
+        assert_eq!(Transform { flip_horizontally: true, ..Transform::default() }, transform);
+
+        let (x, y, tile, transform) = tiles[14];
+        assert_eq!((4, 1), (x, y));
+        assert_eq!(27, tile.id());
+        assert_eq!(Transform::default(), transform);
+
+        assert_eq!(0, map.layer_tiles(99).count());
+    }
+
+    #[test]
+    fn test_draw_list() {
+        let map = Map::load(concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/finite.tmx")).unwrap();
+        let items: Vec<_> = map.draw_list().collect();
+        assert_eq!(151, items.len());
+
+        let first = items.first().unwrap();
+        assert_eq!("below", first.layer.name());
+        assert_eq!((0, 0, 0), (first.x, first.y, first.tile.id()));
+        assert_eq!(Transform { flip_horizontally: true, ..Transform::default() }, first.transform);
+
+        let last = items.last().unwrap();
+        assert_eq!("shape", last.layer.name());
+        assert_eq!((9, 9, 163), (last.x, last.y, last.tile.id()));
+        assert_eq!(Transform::default(), last.transform);
+
+        // Layers in hidden groups are skipped.
+        let xml = include_str!("test_data/finite.tmx").replace(r#"name="group""#, r#"name="group" visible="0""#);
+        let mut hidden = Map::parse_str(&xml).unwrap();
+        hidden.resolve_tilesets(concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data")).unwrap();
+        assert!(hidden.draw_list().all(|item| item.layer.name() != "trees"));
+        assert!(hidden.draw_list().count() < items.len());
+    }
+
+    #[test]
+    fn test_diff() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let mut edited = Map::parse_str(xml).unwrap();
+        assert!(map.diff(&edited).is_empty());
+
+        edited.tile_layer_mut(4).unwrap().set_gid(2, 3, Gid(1)).unwrap();
+        let diff = map.diff(&edited);
+        assert_eq!(BTreeSet::from([3, 4]), diff.changed_layers);
+        assert_eq!(BTreeMap::from([(4, BTreeSet::from([(2, 3)]))]), diff.changed_tiles);
+
+        let xml = xml.replace(r#"<layer id="2" name="above""#, r#"<layer id="20" name="above""#);
+        let renumbered = Map::parse_str(&xml).unwrap();
+        assert_eq!(BTreeSet::from([2, 20]), map.diff(&renumbered).changed_layers);
+
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20" infinite="1">
+                <layer id="1" name="empty" width="1" height="1">
+                    <data encoding="csv"></data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let mut edited = Map::parse_str(xml).unwrap();
+        edited.tile_layer_mut(1).unwrap().set_gid(i32::MAX, i32::MAX, Gid(1)).unwrap();
+        let diff = map.diff(&edited);
+        assert_eq!(BTreeMap::from([(1, BTreeSet::from([(i32::MAX, i32::MAX)]))]), diff.changed_tiles);
+    }
+
+    #[test]
+    fn test_tile_occupancy() {
+        let map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        assert_eq!(Some(Gid(233)), map.first_gid_at(1, 1));
+        assert_eq!(Some(Gid(133)), map.first_gid_at(4, 5));
+        assert_eq!(Some(Gid(86)), map.first_gid_at(9, 3));
+        assert!(map.any_tile_at(0, 0));
+        assert_eq!(None, map.first_gid_at(10, 0));
+        assert!(!map.any_tile_at(-1, 5));
+    }
+
+    #[test]
+    fn test_empty_layer() {
+        let xml = r#"
+            <map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="empty" width="3" height="2">
+                    <data encoding="csv"></data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers()[0].as_tile_layer().unwrap();
+        assert_eq!(TileLayerRegion { x: 0, y: 0, width: 3, height: 2 }, layer.region());
+        assert_eq!(6, layer.gids().count());
+        assert_eq!(0, layer.gids().non_null().count());
+        assert_eq!(Gid::NULL, layer.gid_at(2, 1));
+        assert!(layer.is_empty());
+    }
+
+    #[test]
+    fn test_tile_element_layer_data() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="tiles" width="2" height="2">
+                    <data>
+                        <tile gid="5"/>
+                        <tile/>
+                        <tile gid="2147483654"/>
+                        <tile gid="7"/>
+                    </data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers()[0].as_tile_layer().unwrap();
+        let gids: Vec<Gid> = layer.gids().map(|(_, _, gid)| gid).collect();
+        assert_eq!(vec![Gid(5), Gid::NULL, Gid(2147483654), Gid(7)], gids);
+
+        let result = Map::parse_str(&xml.replace(r#"gid="7""#, r#"gid="seven""#));
+        assert!(matches!(result, Err(Error::InvalidGidError(gid)) if gid == "seven"));
+    }
+
+    #[test]
+    fn test_layer_inherits_map_size() {
+        let gids = ["1"; 100].join(",");
+        let xml = format!(r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <layer id="1" name="sizeless">
+                    <data encoding="csv">{gids}</data>
+                </layer>
+                <group id="2" name="group">
+                    <layer id="3" name="nested">
+                        <data encoding="csv"></data>
+                    </layer>
+                </group>
+            </map>
+        "#);
+        let map = Map::parse_str(&xml).unwrap();
+        let layer = map.layer(1).unwrap().as_tile_layer().unwrap();
+        assert_eq!((10, 10), (layer.width(), layer.height()));
+        assert_eq!(TileLayerRegion { x: 0, y: 0, width: 10, height: 10 }, layer.region());
+        assert_eq!(Gid(1), layer.gid_at(9, 9));
+
+        let nested = map.layer(3).unwrap().as_tile_layer().unwrap();
+        assert_eq!(Some((10, 10)), nested.dimensions());
+        assert_eq!(100, nested.gids().count());
+    }
+
+    #[test]
+    fn test_layer_size_mismatch() {
+        let xml = r#"
+            <map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="short">
+                    <data encoding="csv">1,2</data>
+                </layer>
+            </map>
+        "#;
+        let result = Map::parse_str(xml);
+        assert!(matches!(result, Err(Error::InvalidLayerError)));
+
+        let json = r#"{
+            "orientation": "orthogonal", "width": 3, "height": 2, "tilewidth": 20, "tileheight": 20,
+            "layers": [{ "type": "tilelayer", "id": 1, "name": "long", "data": [1, 2, 3, 4, 5, 6, 7] }]
+        }"#;
+        let result = Map::parse_json_str(json);
+        assert!(matches!(result, Err(Error::InvalidLayerError)));
+    }
+
+    #[test]
+    fn test_tile_layer_is_empty() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="nulls" width="2" height="2">
+                    <data encoding="csv">0,0,0,0</data>
+                </layer>
+                <layer id="2" name="populated" width="2" height="2">
+                    <data encoding="csv">0,0,0,3</data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        assert!(map.layer(1).unwrap().as_tile_layer().unwrap().is_empty());
+        assert!(!map.layer(2).unwrap().as_tile_layer().unwrap().is_empty());
+
+        let map = Map::parse_str(include_str!("test_data/infinite.tmx")).unwrap();
+        assert!(!map.layer(1).unwrap().as_tile_layer().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_flipped_gids() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="1" tilewidth="20" tileheight="20">
+                <layer id="1" name="flipped" width="2" height="1">
+                    <data encoding="csv">4026531841,4294967295</data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers()[0].as_tile_layer().unwrap();
+
+        let gid = layer.gid_at(0, 0);
+        assert_eq!(Gid(0xF0000001), gid);
+        assert_eq!(1, gid.value());
+        let all_flags = Transform {
+            flip_horizontally: true,
+            flip_vertically: true,
+            flip_diagonally: true,
+            rotate_hex_120: true,
+        };
+        assert_eq!(all_flags, gid.transform());
+
+        let gid = layer.gid_at(1, 0);
+        assert_eq!(0x0FFFFFFF, gid.value());
+        assert_eq!(all_flags, gid.transform());
+    }
+
+    #[test]
+    fn test_gids_ordered() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="ordered" width="2" height="2">
+                    <data encoding="csv">1,2,3,4</data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers()[0].as_tile_layer().unwrap();
+        let right_down: Vec<_> = layer.gids_ordered(RenderOrder::RightDown).collect();
+        assert_eq!(vec![(0, 0, Gid(1)), (1, 0, Gid(2)), (0, 1, Gid(3)), (1, 1, Gid(4))], right_down);
+        assert_eq!(layer.gids().collect::<Vec<_>>(), right_down);
+        let left_up: Vec<_> = layer.gids_ordered(RenderOrder::LeftUp).collect();
+        assert_eq!(vec![(1, 1, Gid(4)), (0, 1, Gid(3)), (1, 0, Gid(2)), (0, 0, Gid(1))], left_up);
+    }
+
+    #[test]
+    fn test_parse_bytes() {
+        let xml = include_str!("test_data/finite.tmx");
+        let from_str = Map::parse_str(xml).unwrap();
+        let from_bytes = Map::parse_bytes(xml.as_bytes()).unwrap();
+        assert_eq!(from_str.width(), from_bytes.width());
+        assert_eq!(from_str.tileset_entries().len(), from_bytes.tileset_entries().len());
+        assert_eq!(from_str.all_layers().count(), from_bytes.all_layers().count());
+        let gids = |map: &Map| map.layers()[0].as_tile_layer().unwrap().gids().collect::<Vec<_>>();
+        assert_eq!(gids(&from_str), gids(&from_bytes));
+        assert!(Map::parse_bytes(&[b'<', 0xFF, b'>']).is_err());
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_parse_mmap() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/finite.tmx");
+        let from_str = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        let from_mmap = Map::parse_mmap(path).unwrap();
+        assert_eq!(from_str.width(), from_mmap.width());
+        assert_eq!(from_str.tileset_entries().len(), from_mmap.tileset_entries().len());
+        assert_eq!(from_str.all_layers().count(), from_mmap.all_layers().count());
+        let gids = |map: &Map| map.layers()[0].as_tile_layer().unwrap().gids().collect::<Vec<_>>();
+        assert_eq!(gids(&from_str), gids(&from_mmap));
+    }
+
+    #[test]
+    fn test_parse_prefix() {
+        let xml = r#"<map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20"></map>"#;
+        let mut bytes = xml.as_bytes().to_vec();
+        bytes.extend_from_slice(&[0xFF, 0x00, b'<', b'm']);
+        let (map, used) = Map::parse_prefix(&bytes).unwrap();
+        assert_eq!(xml.len(), used);
+        assert_eq!(3, map.width());
+        assert_eq!(&[0xFF, 0x00, b'<', b'm'], &bytes[used..]);
+        assert!(Map::parse_prefix(&bytes[..used - 1]).is_err());
+
+        let xml = r#"<map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20"/>"#;
+        let (map, used) = Map::parse_prefix(format!("{xml}</map>").as_bytes()).unwrap();
+        assert_eq!(xml.len(), used);
+        assert_eq!(3, map.width());
+
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <!-- A map ends with </map> -->
+            <map orientation="orthogonal" width="3" height="2" tilewidth="20" tileheight="20">
+                <properties>
+                    <property name="tag" value="&lt;/map> />"/>
+                </properties>
+                <![CDATA[<map></map>]]>
+            </map>"#;
+        let (map, used) = Map::parse_prefix(format!("{xml}</map>").as_bytes()).unwrap();
+        assert_eq!(xml.len(), used);
+        assert_eq!(Some("</map> />"), map.properties().get("tag").and_then(|value| value.as_string()));
+    }
+
+    #[test]
+    fn test_chunks() {
+        assert_eq!(Some((0, 0)), TileLayer::chunk_of(0, 15, 16, 16));
+        assert_eq!(Some((-1, 0)), TileLayer::chunk_of(-1, 0, 16, 16));
+        assert_eq!(Some((-1, -1)), TileLayer::chunk_of(-16, -16, 16, 16));
+        assert_eq!(Some((-2, 1)), TileLayer::chunk_of(-17, 16, 16, 16));
+        assert_eq!(None, TileLayer::chunk_of(3, 3, 0, 16));
+        assert_eq!(None, TileLayer::chunk_of(3, 3, 16, 0));
+        assert_eq!(None, TileLayer::chunk_of(3, 3, u32::MAX, 16));
+
+        let xml = include_str!("test_data/infinite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layer(1).unwrap().as_tile_layer().unwrap();
+        let tiles: Vec<_> = layer.tiles_in_chunk(-1, -1, 16, 16).collect();
+        assert_eq!(256, tiles.len());
+        assert_eq!((-16, -16), (tiles[0].0, tiles[0].1));
+        assert_eq!((-1, -1), (tiles[255].0, tiles[255].1));
+        for (x, y, gid) in tiles {
+            assert_eq!(Some((-1, -1)), TileLayer::chunk_of(x, y, 16, 16));
+            assert_eq!(layer.gid_at(x, y), gid);
+        }
+    }
+
+    #[test]
+    fn test_wrong_root_element() {
+        let xml = include_str!("test_data/tilesets/shape.tsx");
+        let result = Map::parse_str(xml);
+        assert!(matches!(result, Err(Error::WrongRootElementError { found }) if found == "tileset"));
+    }
+
+    #[test]
+    fn test_version() {
+        let map = Map::parse_str(r#"<map version="1.10" tiledversion="1.11.0"></map>"#).unwrap();
+        assert_eq!("1.10", map.version());
+        assert_eq!(Some((1, 10)), map.version_tuple());
+        assert_eq!("1.11.0", map.tiled_version());
+        let map = Map::parse_str(r#"<map version="beta"></map>"#).unwrap();
+        assert_eq!(None, map.version_tuple());
+    }
+
+    #[test]
+    fn test_effective_tint() {
+        let xml = r##"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <group id="1" name="group" tintcolor="#ff8000">
+                    <layer id="2" name="tinted" width="1" height="1" tintcolor="#80ff0000">
+                        <data encoding="csv">0</data>
+                    </layer>
+                    <layer id="3" name="untinted" width="1" height="1">
+                        <data encoding="csv">0</data>
+                    </layer>
+                </group>
+                <layer id="4" name="outside" width="1" height="1">
+                    <data encoding="csv">0</data>
+                </layer>
+            </map>
+        "##;
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!(Some(Color { r: 255, g: 0, b: 0, a: 128 }), map.effective_tint(2));
+        assert_eq!(Some(Color { r: 255, g: 128, b: 0, a: 255 }), map.effective_tint(3));
+        assert_eq!(Some(Color::WHITE), map.effective_tint(4));
+        assert_eq!(None, map.effective_tint(5));
+    }
+
+    #[test]
+    fn test_composed_layers() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <group id="1" name="outer" opacity="0.5" offsetx="10" parallaxx="0.5">
+                    <group id="2" name="inner" opacity="0.5" offsety="5" visible="0">
+                        <layer id="3" name="nested" width="1" height="1" opacity="0.8" offsetx="1">
+                            <data encoding="csv">0</data>
+                        </layer>
+                    </group>
+                </group>
+                <layer id="4" name="top" width="1" height="1" opacity="0.8">
+                    <data encoding="csv">0</data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+
+        // A group's opacity lives on the layer containing it, not on the group itself.
+        let outer = map.layer(1).unwrap();
+        assert_eq!(0.5, outer.opacity());
+        assert_eq!(1, outer.as_group_layer().unwrap().layers().len());
+
+        let composed: Vec<_> = map.composed_layers().collect();
+        let names: Vec<&str> = composed.iter().map(|composed| composed.layer.name()).collect();
+        assert_eq!(vec!["outer", "inner", "nested", "top"], names);
+
+        let nested = composed[2];
+        assert_eq!(0.8, nested.layer.opacity());
+        assert_eq!(0.2, nested.opacity);
+        assert_eq!((11.0, 5.0), nested.offset);
+        assert_eq!((0.5, 1.0), nested.parallax);
+        assert!(!nested.visible);
+
+        let top = composed[3];
+        assert_eq!(0.8, top.opacity);
+        assert_eq!((0.0, 0.0), top.offset);
+        assert!(top.visible);
+    }
+
+    #[test]
+    fn test_stagger_axis() {
+        assert_eq!(StaggerAxis::X, "x".parse().unwrap());
+        assert_eq!(StaggerAxis::Y, "y".parse().unwrap());
+        assert!("left-down".parse::<StaggerAxis>().is_err());
+        assert!("X".parse::<StaggerAxis>().is_err());
+        let name = |axis: StaggerAxis| match axis {
+            StaggerAxis::X => "x",
+            StaggerAxis::Y => "y",
+        };
+        assert_eq!("x", name(StaggerAxis::X));
+    }
+
+    #[test]
+    fn test_csv_without_encoding() {
+        let xml = r#"
+            <map orientation="orthogonal" width="2" height="2" tilewidth="20" tileheight="20">
+                <layer id="1" name="csv" width="2" height="2">
+                    <data>
+                        1,2,
+                        3,2147483652
+                    </data>
+                </layer>
+                <layer id="2" name="base64" width="2" height="2">
+                    <data>AQAAAAIAAAADAAAABAAAAA==</data>
+                </layer>
+            </map>
+        "#;
+        let result = Map::parse_str(xml);
+        assert!(matches!(result, Err(Error::DecodeLayerError)));
+
+        let xml = xml.replace(r#"<data>AQAAAAIAAAADAAAABAAAAA==</data>"#, r#"<data encoding="base64">AQAAAAIAAAADAAAABAAAAA==</data>"#);
+        let map = Map::parse_str(&xml).unwrap();
+        let csv_layer = map.layers()[0].as_tile_layer().unwrap();
+        let base64_layer = map.layers()[1].as_tile_layer().unwrap();
+        assert_eq!(Gid(2147483652), csv_layer.gid_at(1, 1));
+        assert_eq!(
+            csv_layer.gids().map(|(_, _, gid)| gid.value()).collect::<Vec<_>>(),
+            base64_layer.gids().map(|(_, _, gid)| gid.value()).collect::<Vec<_>>(),
+        );
+
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <layer id="1" name="single" width="1" height="1">
+                    <data> 5 </data>
+                </layer>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!(Gid(5), map.layers()[0].as_tile_layer().unwrap().gid_at(0, 0));
+    }
+
+    #[test]
+    fn test_local_id() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let external = &map.tileset_entries()[1];
+        let internal = &map.tileset_entries()[2];
+
+        // Range of external tilesets is unknown.
+        assert_eq!(None, external.local_id(Gid(160)));
+        assert_eq!(Some(0), external.local_id(Gid(161)));
+        assert_eq!(Some(1024), external.local_id(Gid(1185)));
+
+        assert_eq!(None, internal.local_id(Gid::NULL));
+        assert_eq!(None, internal.local_id(Gid(1184)));
+        assert_eq!(Some(0), internal.local_id(Gid(1185)));
+        assert_eq!(Some(0), internal.local_id(Gid(1185 | Gid::FLIPPED_HORIZONTALLY_FLAG)));
+        assert_eq!(Some(159), internal.local_id(Gid(1344)));
+        assert_eq!(None, internal.local_id(Gid(1345)));
+        assert!(internal.contains(Gid(1344)));
+        assert!(!internal.contains(Gid(1345)));
+        assert!(!external.contains(Gid(1)));
+    }
+
+    #[test]
+    fn test_dimensions() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layer(1).unwrap().as_tile_layer().unwrap();
+        assert_eq!(Some((10, 10)), layer.dimensions());
+
+        let xml = include_str!("test_data/infinite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layer(1).unwrap().as_tile_layer().unwrap();
+        assert_eq!(None, layer.dimensions());
+        assert_eq!((10, 10), (layer.width(), layer.height()));
+    }
+
+    #[test]
+    fn test_compressed_csv() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <layer id="1" name="compressed" width="1" height="1">
+                    <data encoding="csv" compression="gzip">1</data>
+                </layer>
+            </map>
+        "#;
+        let error = Map::parse_str(xml).unwrap_err();
+        assert!(matches!(error, Error::UnsupportedEncodingError(_)));
+        assert_eq!(
+            "Unsupported layer encoding: CSV data cannot be compressed, but found compression 'gzip'",
+            error.to_string(),
+        );
+    }
+
+    #[test]
+    fn test_csv_crlf() {
+        let xml = "<map width=\"3\" height=\"2\" tilewidth=\"20\" tileheight=\"20\">\r\n\
+            <layer id=\"1\" name=\"crlf\" width=\"3\" height=\"2\">\r\n\
+            <data encoding=\"csv\">\r\n1,2,3,\r\n4,5,6\r\n</data>\r\n\
+            </layer>\r\n\
+            <layer id=\"2\" name=\"trailing\" width=\"3\" height=\"2\">\r\n\
+            <data encoding=\"csv\">\r\n1,2,3,\r\n4,5,6,\r\n</data>\r\n\
+            </layer>\r\n\
+            </map>";
+        let map = Map::parse_str(xml).unwrap();
+        for layer in map.layers() {
+            let gids: Vec<u32> = layer.as_tile_layer().unwrap().gids().map(|(_, _, gid)| gid.0).collect();
+            assert_eq!(vec![1, 2, 3, 4, 5, 6], gids);
+        }
+    }
+
+    #[test]
+    fn test_kind_name() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <imagelayer id="1" name="background"/>
+                <group id="2" name="group">
+                    <layer id="3" name="ground" width="1" height="1">
+                        <data encoding="csv">0</data>
+                    </layer>
+                </group>
+                <objectgroup id="4" name="objects"/>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let kind_names: Vec<&str> = map.all_layers().map(|layer| layer.kind_name()).collect();
+        assert_eq!(vec!["image", "group", "tile", "object"], kind_names);
+    }
+
+    #[test]
+    fn test_image_sources() {
+        let map = Map::load(concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/infinite.tmx")).unwrap();
+        let expected = vec![
+            "vikings_of_midgard.png",
+            "shape.png",
+            "tilesets/vikings_of_midgard_alt.png",
+            "images/pepe.png",
+        ];
+        assert_eq!(expected, map.image_sources());
+    }
+
+    #[test]
+    fn test_next_ids() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!(8, map.next_layer_id());
+        assert_eq!(19, map.next_object_id());
+
+        let json = include_str!("test_data/finite.tmj");
+        let map = Map::parse_json_str(json).unwrap();
+        assert_eq!(8, map.next_layer_id());
+        assert_eq!(19, map.next_object_id());
+    }
+
+    #[test]
+    fn test_utf16() {
+        let xml = include_str!("test_data/finite.tmx").replacen("UTF-8", "UTF-16", 1);
+        let expected = Map::parse_str(&xml).unwrap();
+
+        let mut le_bytes = vec![0xFF, 0xFE];
+        le_bytes.extend(xml.encode_utf16().flat_map(u16::to_le_bytes));
+        let map = Map::parse(le_bytes.as_slice()).unwrap();
+        assert_eq!(expected.width(), map.width());
+        assert_eq!(expected.layers().len(), map.layers().len());
+
+        let mut be_bytes = vec![0xFE, 0xFF];
+        be_bytes.extend(xml.encode_utf16().flat_map(u16::to_be_bytes));
+        let map = Map::parse(be_bytes.as_slice()).unwrap();
+        assert_eq!(expected.tileset_entries().len(), map.tileset_entries().len());
+
+        assert!(matches!(Map::parse(&le_bytes[..le_bytes.len() - 1]), Err(Error::IOError(_))));
+    }
+
+    #[test]
+    fn test_embedded_tileset_attributes() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <tileset firstgid="5" name="embedded" tilewidth="20" tileheight="20" tilecount="1" columns="1" tilerendersize="grid" fillmode="preserve-aspect-fit">
+                    <image source="embedded.png" width="20" height="20"/>
+                </tileset>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let entry = &map.tileset_entries()[0];
+        assert_eq!(5, entry.first_gid());
+        assert_eq!(None, entry.source());
+        let TilesetEntryKind::Internal(tileset) = entry.kind() else { panic!("tileset not embedded") };
+        assert_eq!("embedded", tileset.name());
+        assert_eq!(TileRenderSize::Grid, tileset.tile_render_size());
+        assert_eq!(FillMode::PreserveAspectFit, tileset.fill_mode());
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn test_gzipped_file() {
+        use std::io::Write;
+        let xml = include_str!("test_data/finite.tmx");
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(xml.as_bytes()).unwrap();
+        let gzipped = encoder.finish().unwrap();
+
+        let expected = Map::parse_str(xml).unwrap();
+        let map = Map::parse(gzipped.as_slice()).unwrap();
+        assert!(expected.diff(&map).is_empty());
+        assert_eq!(expected.tileset_entries().len(), map.tileset_entries().len());
+        assert_eq!(expected.properties(), map.properties());
+
+        let dir = std::env::temp_dir().join(format!("tiled_parser_gzipped_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let gzip = |text: &str| {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(text.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let map_xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <tileset firstgid="1" source="tiles.tsx.gz"/>
+            </map>
+        "#;
+        let tileset_xml = r#"<tileset name="tiles" tilewidth="20" tileheight="20" tilecount="0" columns="0"/>"#;
+        std::fs::write(dir.join("map.tmx.gz"), gzip(map_xml)).unwrap();
+        std::fs::write(dir.join("tiles.tsx.gz"), gzip(tileset_xml)).unwrap();
+        let result = Map::load(dir.join("map.tmx.gz"));
+        std::fs::remove_dir_all(&dir).unwrap();
+        let map = result.unwrap();
+        let TilesetEntryKind::Internal(tileset) = map.tileset_entries()[0].kind() else { panic!("tileset not resolved") };
+        assert_eq!("tiles", tileset.name());
+    }
+
+    #[test]
+    fn test_compression_level() {
+        let xml = r#"<map orientation="orthogonal" compressionlevel="6" width="1" height="1" tilewidth="20" tileheight="20"/>"#;
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!(Some(6), map.compression_level());
+
+        let json = r#"{ "orientation": "orthogonal", "compressionlevel": -1, "width": 1, "height": 1, "tilewidth": 20, "tileheight": 20 }"#;
+        let map = Map::parse_json_str(json).unwrap();
+        assert_eq!(Some(-1), map.compression_level());
+
+        let map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        assert_eq!(None, map.compression_level());
+    }
+
+    #[test]
+    fn test_bool_spellings() {
+        for (value, expected) in [("0", false), ("1", true), ("false", false), ("true", true)] {
+            let xml = format!(r#"
+                <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20" infinite="{value}">
+                    <objectgroup id="1" name="objects" visible="{value}">
+                        <object id="1" x="0" y="0" visible="{value}">
+                            <text bold="{value}">hello</text>
+                        </object>
+                    </objectgroup>
+                </map>
+            "#);
+            let map = Map::parse_str(&xml).unwrap();
+            assert_eq!(expected, map.infinite());
+            let layer = &map.layers()[0];
+            assert_eq!(expected, layer.visible());
+            let object = &layer.as_object_group_layer().unwrap().objects()[0];
+            assert_eq!(expected, object.visible());
+            let ObjectKind::Text(text) = object.kind() else { panic!("not a text object") };
+            assert_eq!(expected, text.bold());
+        }
+        let xml = r#"<map><objectgroup id="1" visible="yes"/></map>"#;
+        assert!(matches!(Map::parse_str(xml), Err(Error::ParsingError)));
+    }
+
+    #[test]
+    fn test_layer_and_object_bools_match() {
+        for value in ["0", "1", "false", "true", "TRUE", "yes", ""] {
+            let layer_xml = format!(r#"<map><objectgroup id="1" visible="{value}"/></map>"#);
+            let object_xml = format!(r#"<map><objectgroup id="1"><object id="1" visible="{value}"/></objectgroup></map>"#);
+            let layer_visible = Map::parse_str(&layer_xml).map(|map| map.layers()[0].visible());
+            let object_visible = Map::parse_str(&object_xml)
+                .map(|map| map.layers()[0].as_object_group_layer().unwrap().objects()[0].visible());
+            match (layer_visible, object_visible) {
+                (Ok(layer_visible), Ok(object_visible)) => assert_eq!(layer_visible, object_visible, "{value}"),
+                (Err(Error::ParsingError), Err(Error::ParsingError)) => {},
+                (layer_visible, object_visible) => panic!("'{value}' parsed as {layer_visible:?} and {object_visible:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_tile_size() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!((map.tile_width(), map.tile_height()), map.tile_size());
+        let TilesetEntryKind::Internal(tileset) = map.tileset_entries()[2].kind() else { panic!("external tileset") };
+        assert_eq!((tileset.tile_width(), tileset.tile_height()), tileset.tile_size());
+    }
+
+    #[test]
+    fn test_tile_layer_debug() {
+        let size = 500;
+        let csv = vec!["1"; size * size].join(",");
+        let xml = format!(r#"
+            <map orientation="orthogonal" width="{size}" height="{size}" tilewidth="20" tileheight="20">
+                <layer id="1" name="large" width="{size}" height="{size}">
+                    <data encoding="csv">{csv}</data>
+                </layer>
+            </map>
+        "#);
+        let map = Map::parse_str(&xml).unwrap();
+        let debug = format!("{map:#?}");
+        assert!(debug.len() < 2000, "{debug}");
+        assert!(debug.contains("non_null_count: 250000"));
+        assert_eq!(size * size, map.layers()[0].as_tile_layer().unwrap().gids().count());
+    }
+
+    #[test]
+    fn test_external_tileset_extra_attributes() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <tileset firstgid="1" source="tilesets/shape.tsx" objectalignment="bogus" tilerendersize="grid" unexpected="1"/>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let entry = &map.tileset_entries()[0];
+        assert_eq!(1, entry.first_gid());
+        assert_eq!(Some("tilesets/shape.tsx"), entry.source());
+        assert!(matches!(entry.kind(), TilesetEntryKind::External(_)));
+
+        let json = r#"{ "tilesets": [{ "firstgid": 1, "source": "tilesets/shape.tsj", "objectalignment": "bogus", "unexpected": [1] }] }"#;
+        let map = Map::parse_json_str(json).unwrap();
+        assert_eq!(Some("tilesets/shape.tsj"), map.tileset_entries()[0].source());
+    }
 
     #[test]
     fn test_finite() {
@@ -320,6 +1705,114 @@ mod test {
         assert_eq!(Gid(98), tile_layer.gid_at(5, 2));
     }
 
+    #[test]
+    fn test_bounds() {
+        let xml = include_str!("test_data/infinite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let expected = TileLayerRegion { x: -16, y: -16, width: 32, height: 32 };
+        assert_eq!(expected, map.bounds());
+        assert_eq!((-320.0, -320.0, 640.0, 640.0), map.pixel_bounds());
+
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let expected = TileLayerRegion { x: 0, y: 0, width: 10, height: 10 };
+        assert_eq!(expected, map.bounds());
+        let (x, y, width, height) = map.pixel_bounds();
+        assert_eq!(-61.3333, x);
+        // The polygon "steve" reaches highest.
+        assert_eq!(-78.3333 - 10.3333, y);
+        assert_eq!(395.209 + 102.735 + 61.3333, width);
+        assert_eq!(200.0 - y, height);
+    }
+
+    #[test]
+    fn test_pixel_bounds_object_shapes() {
+        let xml = r#"
+            <map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="16" tileheight="16" infinite="0" nextlayerid="2" nextobjectid="3">
+             <objectgroup id="1" name="Objects">
+              <object id="1" x="8" y="8">
+               <polygon points="0,0 40,-20 -16,50"/>
+              </object>
+              <object id="2" gid="1" x="-4" y="4" width="16" height="16"/>
+             </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!((-8.0, -12.0, 56.0, 70.0), map.pixel_bounds());
+    }
+
+    #[test]
+    fn test_load() {
+        let tmx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/finite.tmx");
+        let tmj_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/finite.tmj");
+        let tmx_map = Map::load(tmx_path).unwrap();
+        let tmj_map = Map::load(tmj_path).unwrap();
+        assert_eq!(tmx_map.width(), tmj_map.width());
+        assert_eq!(tmx_map.tile_width(), tmj_map.tile_width());
+
+        // External tilesets are resolved.
+        for map in [&tmx_map, &tmj_map] {
+            let entry = &map.tileset_entries()[0];
+            let TilesetEntryKind::Internal(tileset) = entry.kind() else { panic!("tileset not resolved") };
+            assert_eq!("vikings_of_midgard", tileset.name());
+            assert!(entry.source().is_some());
+            let TilesetEntryKind::Internal(tileset) = map.tileset_entries()[2].kind() else { panic!() };
+            assert_eq!(TileRenderSize::Grid, tileset.tile_render_size());
+        }
+
+        // Layers match.
+        let tmx_names: Vec<&str> = tmx_map.all_layers().map(|layer| layer.name()).collect();
+        let tmj_names: Vec<&str> = tmj_map.all_layers().map(|layer| layer.name()).collect();
+        assert_eq!(tmx_names, tmj_names);
+        let tmx_layer = tmx_map.layers()[0].as_tile_layer().unwrap();
+        let tmj_layer = tmj_map.layers()[0].as_tile_layer().unwrap();
+        assert!(tmx_layer.gids().eq(tmj_layer.gids()));
+        let tmx_objects = tmx_map.layers()[4].as_object_group_layer().unwrap();
+        let tmj_objects = tmj_map.layers()[4].as_object_group_layer().unwrap();
+        assert_eq!(tmx_objects.color(), tmj_objects.color());
+        assert_eq!(tmx_objects.objects().len(), tmj_objects.objects().len());
+        for (tmx_object, tmj_object) in tmx_objects.objects().iter().zip(tmj_objects.objects()) {
+            assert_eq!(tmx_object.x(), tmj_object.x());
+            assert_eq!(tmx_object.properties().get("name"), tmj_object.properties().get("name"));
+        }
+    }
+
+    #[test]
+    fn test_load_unsupported() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/tilesets/image_sources.txt");
+        assert!(matches!(Map::load(path), Err(Error::UnsupportedFileError)));
+    }
+
+    #[test]
+    fn test_bom() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(&format!("\u{feff}{xml}")).unwrap();
+        assert_eq!(10, map.width());
+        let map = Map::parse(format!("\u{feff}{xml}").as_bytes()).unwrap();
+        assert_eq!(10, map.width());
+    }
+
+    #[test]
+    fn test_background_color() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        assert_eq!(None, map.background_color());
+
+        let xml = xml.replace("infinite=\"0\"", "infinite=\"0\" backgroundcolor=\"#00000000\"");
+        let map = Map::parse_str(&xml).unwrap();
+        assert_eq!(Some(Color::TRANSPARENT), map.background_color());
+    }
+
+    #[test]
+    fn test_class() {
+        let map = Map::parse_str(r#"<map class="dungeon"><layer id="1" class="floor" width="1" height="1"><data encoding="csv">0</data></layer></map>"#).unwrap();
+        assert_eq!("dungeon", map.class());
+        assert_eq!("floor", map.layers()[0].class());
+        let map = Map::parse_str(r#"<map type="dungeon"><layer id="1" type="floor" width="1" height="1"><data encoding="csv">0</data></layer></map>"#).unwrap();
+        assert_eq!("dungeon", map.class());
+        assert_eq!("floor", map.layers()[0].class());
+    }
+
     #[test]
     fn test_hexagonal() {
         let xml = include_str!("test_data/hexagonal.tmx");
@@ -366,6 +1859,31 @@ mod test {
         assert_eq!("images/pepe.png", image_layer.image().source());
     }
 
+    #[test]
+    fn test_layer_offset() {
+        let xml = include_str!("test_data/infinite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let layer = map.layers().iter().find(|layer| layer.name() == "background").unwrap();
+        assert_eq!((-134.0, -145.333), layer.offset());
+        assert_eq!((layer.offset_x(), layer.offset_y()), layer.offset());
+        assert_eq!((0.5, 0.5), layer.parallax());
+        assert_eq!((layer.parallax_x(), layer.parallax_y()), layer.parallax());
+    }
+
+    #[test]
+    fn test_object_layer_common_fields() {
+        let xml = include_str!("test_data/finite.tmx").replace(
+            "<objectgroup color=\"#00eaff\" id=\"7\" name=\"objects\">",
+            "<objectgroup color=\"#00eaff\" id=\"7\" name=\"objects\" opacity=\"0.5\" visible=\"0\">",
+        );
+        let map = Map::parse_str(&xml).unwrap();
+        let layer = map.layers().iter().find(|layer| layer.name() == "objects").unwrap();
+        assert_eq!(0.5, layer.opacity());
+        assert!(!layer.visible());
+        let object_layer = layer.as_object_group_layer().unwrap();
+        assert_eq!(Some(Color { r: 0x00, g: 0xea, b: 0xff, a: 0xff }), object_layer.color());
+    }
+
     #[test]
     fn test_object_layer() {
         let xml = include_str!("test_data/finite.tmx");
@@ -374,4 +1892,140 @@ mod test {
         let object_layer = layer.as_object_group_layer().unwrap();
         println!("{object_layer:#?}");
     }
+
+    #[test]
+    fn test_write_json() {
+        let map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        let mut json = Vec::new();
+        map.write_json(&mut json).unwrap();
+        let written = Map::parse_json_str(std::str::from_utf8(&json).unwrap()).unwrap();
+
+        assert!(map.diff(&written).is_empty());
+        assert_eq!(map.version(), written.version());
+        assert_eq!(map.orientation(), written.orientation());
+        assert_eq!(map.render_order(), written.render_order());
+        assert_eq!(map.tile_size(), written.tile_size());
+        assert_eq!((map.width(), map.height()), (written.width(), written.height()));
+        assert_eq!(map.next_object_id(), written.next_object_id());
+
+        let first_gids = |map: &Map| map.tileset_entries().iter().map(|entry| (entry.first_gid(), entry.source().map(String::from))).collect::<Vec<_>>();
+        assert_eq!(first_gids(&map), first_gids(&written));
+        let TilesetEntryKind::Internal(tileset) = written.tileset_entries()[2].kind() else { panic!("tileset should be embedded") };
+        assert_eq!("vikings_of_midgard_alt", tileset.name());
+        assert_eq!((1, 2), (tileset.tile_offset().x, tileset.tile_offset().y));
+        assert_eq!(25, tileset.grid().unwrap().width);
+        assert_eq!(TileRenderSize::Grid, tileset.tile_render_size());
+        assert_eq!(FillMode::PreserveAspectFit, tileset.fill_mode());
+        assert_eq!(Some((320, 200)), tileset.image_dimensions());
+        assert_eq!(160, tileset.tiles().count());
+        assert_eq!(Some(true), tileset.tile(0).unwrap().properties().get("is_steve").unwrap().as_bool());
+    }
+
+    #[test]
+    fn test_projection() {
+        let map = Map::parse_str(include_str!("test_data/hexagonal.tmx")).unwrap();
+        let expected = MapProjection {
+            orientation: Orientation::Hexagonal,
+            tile_width: 20,
+            tile_height: 20,
+            stagger_axis: Some(StaggerAxis::Y),
+            stagger_index: Some(StaggerIndex::Odd),
+            hex_side_length: Some(10),
+            render_order: RenderOrder::RightDown,
+        };
+        assert_eq!(expected, map.projection());
+    }
+
+    #[test]
+    fn test_effective_render_order() {
+        let map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        assert_eq!(Some(RenderOrder::RightDown), map.effective_render_order());
+
+        let map = Map::parse_str(include_str!("test_data/isometric.tmx")).unwrap();
+        assert_eq!(Orientation::Isometric, map.orientation());
+        assert_eq!(None, map.effective_render_order());
+    }
+
+    #[test]
+    fn test_gid_histogram() {
+        let mut map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        let histogram = map.gid_histogram();
+        assert_eq!(Some(&61), histogram.get(&Gid(1)));
+        assert_eq!(Some(&14), histogram.get(&Gid(324)));
+        assert_eq!(Some(&10), histogram.get(&Gid(133)));
+        assert_eq!(None, histogram.get(&Gid::NULL));
+        assert_eq!(None, histogram.get(&Gid(2)));
+
+        let flipped = Gid(324 | Gid::FLIPPED_HORIZONTALLY_FLAG);
+        map.tile_layer_mut(1).unwrap().set_gid(0, 0, flipped).unwrap();
+        assert_eq!(Some(&15), map.gid_histogram().get(&Gid(324)));
+    }
+
+    #[test]
+    fn test_is_layer_visible() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <group id="1" name="hidden" visible="0">
+                    <layer id="2" name="visible_in_hidden" width="1" height="1">
+                        <data encoding="csv">0</data>
+                    </layer>
+                </group>
+                <group id="3" name="shown">
+                    <layer id="4" name="visible_in_shown" width="1" height="1">
+                        <data encoding="csv">0</data>
+                    </layer>
+                    <layer id="5" name="hidden_in_shown" width="1" height="1" visible="0">
+                        <data encoding="csv">0</data>
+                    </layer>
+                </group>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        assert!(map.layer(2).unwrap().visible());
+        assert_eq!(Some(false), map.is_layer_visible(1));
+        assert_eq!(Some(false), map.is_layer_visible(2));
+        assert_eq!(Some(true), map.is_layer_visible(3));
+        assert_eq!(Some(true), map.is_layer_visible(4));
+        assert_eq!(Some(false), map.is_layer_visible(5));
+        assert_eq!(None, map.is_layer_visible(6));
+    }
+
+    #[test]
+    fn test_duplicate_first_gid() {
+        let xml = include_str!("test_data/finite.tmx").replace("firstgid=\"161\"", "firstgid=\"1\"");
+        let error = Map::parse_str(&xml).unwrap_err();
+        assert!(matches!(error, Error::DuplicateFirstGidError(1)));
+
+        let json = r#"{
+            "orientation": "orthogonal", "width": 1, "height": 1, "tilewidth": 20, "tileheight": 20,
+            "tilesets": [{ "firstgid": 1, "source": "a.tsj" }, { "firstgid": 1, "source": "b.tsj" }]
+        }"#;
+        let error = Map::parse_json_str(json).unwrap_err();
+        assert!(matches!(error, Error::DuplicateFirstGidError(1)));
+    }
+
+    #[test]
+    fn test_tileset_entry_for_gid() {
+        let xml = include_str!("test_data/finite.tmx");
+        let map = Map::parse_str(xml).unwrap();
+        let first_gid = |gid: Gid| map.tileset_entry_for_gid(gid).map(|entry| entry.first_gid());
+        assert_eq!(None, first_gid(Gid::NULL));
+        assert_eq!(Some(1), first_gid(Gid(1)));
+        assert_eq!(Some(1), first_gid(Gid(160)));
+        assert_eq!(Some(161), first_gid(Gid(161)));
+        assert_eq!(Some(161), first_gid(Gid(1184)));
+        assert_eq!(Some(1185), first_gid(Gid(1185)));
+        assert_eq!(Some(1185), first_gid(Gid(5000)));
+        assert_eq!(Some(161), first_gid(Gid(161 | Gid::FLIPPED_VERTICALLY_FLAG)));
+        assert_eq!(Some("tilesets/shape.tsx"), map.tileset_entry_for_gid(Gid(200)).unwrap().source());
+
+        // Entries out of order still cover the gids up to the next larger first gid.
+        let map = Map::parse_str(&xml.replace("firstgid=\"161\"", "firstgid=\"2000\"")).unwrap();
+        assert!(map.validate_tilesets().is_err());
+        let first_gid = |gid: Gid| map.tileset_entry_for_gid(gid).map(|entry| entry.first_gid());
+        assert_eq!(Some(1), first_gid(Gid(1184)));
+        assert_eq!(Some(1185), first_gid(Gid(1999)));
+        assert_eq!(Some(2000), first_gid(Gid(2000)));
+        assert_eq!(Some((2, 814)), map.tile_location_of(Gid(1999)));
+    }
 }
\ No newline at end of file
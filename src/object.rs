@@ -1,9 +1,10 @@
 use std::str::FromStr;
 use roxmltree::Node;
-use crate::{parse_bool, Color, Error, Gid, Properties, Result};
+use serde_json::{json, Value};
+use crate::{json_array, json_bool, json_f32, json_object, json_str, json_u32, parse_bool, parse_coordinate, Color, Error, Gid, JsonObject, Map, Properties, Result, TilesetEntryKind};
 
 /// A group of [`Object`]s.
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub struct ObjectGroupLayer {
     color: Option<Color>,
     draw_order: DrawOrder,
@@ -15,6 +16,17 @@ impl ObjectGroupLayer {
     pub fn color(&self) -> Option<Color> { self.color }
     pub fn draw_order(&self) -> DrawOrder { self.draw_order }
     pub fn objects(&self) -> &[Object] { &self.objects }
+    pub fn objects_mut(&mut self) -> &mut [Object] { &mut self.objects }
+
+    /// Objects in the order Tiled renders them.
+    /// Sorted by y coordinate when the draw order is [`DrawOrder::TopDown`], and in index order otherwise.
+    pub fn objects_in_draw_order(&self) -> Vec<&Object> {
+        let mut objects: Vec<&Object> = self.objects.iter().collect();
+        if self.draw_order == DrawOrder::TopDown {
+            objects.sort_by(|a, b| a.y.total_cmp(&b.y));
+        }
+        objects
+    }
 
     pub(crate) fn parse(object_layer_node: Node) -> Result<Self> {
         let mut result = Self::default();
@@ -26,17 +38,42 @@ impl ObjectGroupLayer {
             }
         }
         for child in object_layer_node.children() {
-            match child.tag_name().name() {
-                "object" => result.objects.push(Object::parse(child)?),
+            if child.tag_name().name() == "object" {
+                result.objects.push(Object::parse(child)?);
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn parse_json(object_layer_object: &JsonObject) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in object_layer_object {
+            match name.as_str() {
+                "color" => result.color = Some(json_str(value)?.parse()?),
+                "draworder" => result.draw_order = json_str(value)?.parse()?,
+                "objects" => {
+                    for object_value in json_array(value)? {
+                        result.objects.push(Object::parse_json(object_value)?);
+                    }
+                },
                 _ => {}
             }
         }
         Ok(result)
     }
+
+    /// Writes the layer's objects into a JSON layer object.
+    pub(crate) fn write_json(&self, object_layer_object: &mut JsonObject) {
+        if let Some(color) = self.color {
+            object_layer_object.insert("color".into(), color.to_string().into());
+        }
+        object_layer_object.insert("draworder".into(), self.draw_order.as_str().into());
+        object_layer_object.insert("objects".into(), self.objects.iter().map(Object::to_json).collect());
+    }
 }
 
 /// A single object in an [`ObjectGroupLayer`]
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Object {
     id: u32,
     name: String,
@@ -74,37 +111,124 @@ impl Default for Object {
 impl Object {
     pub fn id(&self) -> u32 { self.id }
     pub fn name(&self) -> &str { &self.name }
+    /// Class of the object, read from either the `type` or `class` attribute.
     pub fn typ(&self) -> &str { &self.typ }
     pub fn x(&self) -> f32 { self.x }
     pub fn y(&self) -> f32 { self.y }
     pub fn width(&self) -> f32 { self.width }
     pub fn height(&self) -> f32 { self.height }
+    pub fn position(&self) -> (f32, f32) { (self.x, self.y) }
+
+    pub fn set_position(&mut self, x: f32, y: f32) {
+        self.x = x;
+        self.y = y;
+    }
+
+    /// Width and height of the object.
+    /// Always (0.0, 0.0) for point objects.
+    pub fn size(&self) -> (f32, f32) {
+        match self.kind {
+            ObjectKind::Point => (0.0, 0.0),
+            _ => (self.width, self.height),
+        }
+    }
+
+    /// Size of the object, falling back to the size of its tile for tile objects that omit their width or height.
+    /// Tiled writes the size of tile objects, so this only matters for hand-written or older files.
+    pub fn effective_size(&self, map: &Map) -> (f32, f32) {
+        let (width, height) = self.size();
+        if width != 0.0 && height != 0.0 {
+            return (width, height);
+        }
+        let Some((tile_width, tile_height)) = self.tile_size(map) else { return (width, height) };
+        let width = if width == 0.0 { tile_width as f32 } else { width };
+        let height = if height == 0.0 { tile_height as f32 } else { height };
+        (width, height)
+    }
+
+    /// Size of the tile of a tile object.
+    /// None if the object has no gid, or its tileset is missing or unresolved.
+    fn tile_size(&self, map: &Map) -> Option<(u32, u32)> {
+        let (tileset_idx, tile_id) = map.tile_location_of(self.gid?)?;
+        let TilesetEntryKind::Internal(tileset) = map.tileset_entries()[tileset_idx].kind() else { return None };
+        Some(tileset.tile_or_default(tile_id).effective_size(tileset))
+    }
+
+    /// Geometry of the object in the coordinates of its layer.
+    /// Tile objects are anchored at their bottom-left corner, so their rectangle extends upwards from y.
+    /// Rotation is not applied.
+    pub fn shape(&self) -> Shape {
+        let (x, y) = (self.x, self.y);
+        let absolute = |points: &[(f32, f32)]| points.iter().map(|(px, py)| (x + px, y + py)).collect();
+        match &self.kind {
+            ObjectKind::Rectangle if self.gid.is_some() => Shape::Rect { x, y: y - self.height, width: self.width, height: self.height },
+            ObjectKind::Rectangle => Shape::Rect { x, y, width: self.width, height: self.height },
+            ObjectKind::Point => Shape::Point { x, y },
+            ObjectKind::Ellipse => {
+                let radius_x = self.width / 2.0;
+                let radius_y = self.height / 2.0;
+                Shape::Ellipse { center_x: x + radius_x, center_y: y + radius_y, radius_x, radius_y }
+            },
+            ObjectKind::Polyline(points) => Shape::Polyline(absolute(points)),
+            ObjectKind::Polygon(points) => Shape::Polygon(absolute(points)),
+            ObjectKind::Text(_) => Shape::Text { x, y, width: self.width, height: self.height },
+        }
+    }
+
+    /// Smallest rectangle containing the object's [`shape`](Self::shape), as (min_x, min_y, max_x, max_y).
+    /// Rotation is not applied.
+    pub(crate) fn bounds(&self) -> (f32, f32, f32, f32) {
+        match self.shape() {
+            Shape::Rect { x, y, width, height } | Shape::Text { x, y, width, height } => (x, y, x + width, y + height),
+            Shape::Ellipse { center_x, center_y, radius_x, radius_y } => {
+                (center_x - radius_x, center_y - radius_y, center_x + radius_x, center_y + radius_y)
+            },
+            Shape::Point { x, y } => (x, y, x, y),
+            Shape::Polygon(points) | Shape::Polyline(points) => points.iter().fold(
+                (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+                |(min_x, min_y, max_x, max_y), &(x, y)| (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y)),
+            ),
+        }
+    }
+
     pub fn rotation(&self) -> f32 { self.rotation }
     pub fn gid(&self) -> Option<Gid> { self.gid }
     pub fn visible(&self) -> bool { self.visible }
     pub fn properties(&self) -> &Properties { &self.properties }
+    pub fn properties_mut(&mut self) -> &mut Properties { &mut self.properties }
     pub fn kind(&self) -> &ObjectKind { &self.kind }
 
+    /// True for rectangles, including tile objects.
+    pub fn is_rectangle(&self) -> bool { matches!(self.kind, ObjectKind::Rectangle) }
+    pub fn is_point(&self) -> bool { matches!(self.kind, ObjectKind::Point) }
+    pub fn is_ellipse(&self) -> bool { matches!(self.kind, ObjectKind::Ellipse) }
+    pub fn is_polyline(&self) -> bool { matches!(self.kind, ObjectKind::Polyline(_)) }
+    pub fn is_polygon(&self) -> bool { matches!(self.kind, ObjectKind::Polygon(_)) }
+    pub fn is_text(&self) -> bool { matches!(self.kind, ObjectKind::Text(_)) }
+
     fn parse(object_node: Node) -> Result<Self> {
         let mut result = Self::default();
         for attr in object_node.attributes() {
             match attr.name() {
                 "id" => result.id = attr.value().parse()?,
                 "name" => result.name = attr.value().into(),
-                "type" => result.typ = attr.value().into(),
-                "x" => result.x = attr.value().parse()?,
-                "y" => result.y = attr.value().parse()?,
-                "width" => result.width = attr.value().parse()?,
-                "height" => result.height = attr.value().parse()?,
+                "type" | "class" => result.typ = attr.value().into(),
+                "x" => result.x = parse_coordinate(attr.value())?,
+                "y" => result.y = parse_coordinate(attr.value())?,
+                "width" => result.width = parse_coordinate(attr.value())?,
+                "height" => result.height = parse_coordinate(attr.value())?,
                 "rotation" => result.rotation = attr.value().parse()?,
-                "gid" => result.gid = Some(Gid(attr.value().parse()?)),
-                "visible" => result.visible = attr.value().parse()?,
+                "gid" => {
+                    let gid = attr.value().parse().map_err(|_| Error::InvalidGidError(attr.value().into()))?;
+                    result.gid = Some(Gid(gid));
+                },
+                "visible" => result.visible = parse_bool(attr.value())?,
                 _ => {}
             }
         }
         for child in object_node.children() {
             match child.tag_name().name() {
-                "properties" => result.properties = Properties::parse(child)?,
+                "properties" => result.properties.merge(Properties::parse(child)?),
                 "ellipse" => result.kind = ObjectKind::Ellipse,
                 "point" => result.kind = ObjectKind::Point,
                 "polyline" => result.kind = ObjectKind::parse_polyline(child)?,
@@ -115,6 +239,63 @@ impl Object {
         }
         Ok(result)
     }
+
+    fn parse_json(object_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(object_value)? {
+            match name.as_str() {
+                "id" => result.id = json_u32(value)?,
+                "name" => result.name = json_str(value)?.into(),
+                "type" | "class" => result.typ = json_str(value)?.into(),
+                "x" => result.x = json_f32(value)?,
+                "y" => result.y = json_f32(value)?,
+                "width" => result.width = json_f32(value)?,
+                "height" => result.height = json_f32(value)?,
+                "rotation" => result.rotation = json_f32(value)?,
+                "gid" => {
+                    let gid = json_u32(value).map_err(|_| Error::InvalidGidError(value.to_string()))?;
+                    result.gid = Some(Gid(gid));
+                },
+                "visible" => result.visible = json_bool(value)?,
+                "properties" => result.properties = Properties::parse_json(value)?,
+                "ellipse" if json_bool(value)? => result.kind = ObjectKind::Ellipse,
+                "point" if json_bool(value)? => result.kind = ObjectKind::Point,
+                "polyline" => result.kind = ObjectKind::Polyline(parse_json_points(value)?),
+                "polygon" => result.kind = ObjectKind::Polygon(parse_json_points(value)?),
+                "text" => result.kind = ObjectKind::Text(Text::parse_json(value)?),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("id".into(), self.id.into());
+        object.insert("name".into(), self.name.as_str().into());
+        object.insert("type".into(), self.typ.as_str().into());
+        object.insert("x".into(), self.x.into());
+        object.insert("y".into(), self.y.into());
+        object.insert("width".into(), self.width.into());
+        object.insert("height".into(), self.height.into());
+        object.insert("rotation".into(), self.rotation.into());
+        object.insert("visible".into(), self.visible.into());
+        if let Some(gid) = self.gid {
+            object.insert("gid".into(), gid.0.into());
+        }
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        match &self.kind {
+            ObjectKind::Rectangle => {},
+            ObjectKind::Point => { object.insert("point".into(), true.into()); },
+            ObjectKind::Ellipse => { object.insert("ellipse".into(), true.into()); },
+            ObjectKind::Polyline(points) => { object.insert("polyline".into(), points_to_json(points)); },
+            ObjectKind::Polygon(points) => { object.insert("polygon".into(), points_to_json(points)); },
+            ObjectKind::Text(text) => { object.insert("text".into(), text.to_json()); },
+        }
+        Value::Object(object)
+    }
 }
 
 /// The draw order of objects in a [`GroupLayer`](crate::GroupLayer).
@@ -136,9 +317,22 @@ impl FromStr for DrawOrder {
     }
 }
 
+impl DrawOrder {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Index => "index",
+            Self::TopDown => "topdown",
+        }
+    }
+}
+
 
 /// A specific type of [`Object`].
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, PartialEq, Debug, Default)]
 pub enum ObjectKind {
     #[default]
     Rectangle,
@@ -150,6 +344,25 @@ pub enum ObjectKind {
 }
 
 impl ObjectKind {
+    /// Signed area of a polygon, using the shoelace formula.
+    /// Since y points down in Tiled, the area is positive when points wind clockwise on screen.
+    /// None if this is not a polygon.
+    pub fn polygon_area(&self) -> Option<f32> {
+        let ObjectKind::Polygon(points) = self else { return None };
+        let doubled_area: f32 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|((x1, y1), (x2, y2))| x1 * y2 - x2 * y1)
+            .sum();
+        Some(doubled_area / 2.0)
+    }
+
+    /// True if the points of a polygon wind clockwise on screen.
+    /// None if this is not a polygon.
+    pub fn is_clockwise(&self) -> Option<bool> {
+        self.polygon_area().map(|area| area > 0.0)
+    }
+
     fn parse_polyline(node: Node) -> Result<Self> {
         let mut result = Vec::new();
         if let Some(points) = node.attribute("points") {
@@ -167,6 +380,20 @@ impl ObjectKind {
     }
 }
 
+/// Concrete geometry of an [`Object`], as returned by [`Object::shape`].
+#[derive(Clone, PartialEq, Debug)]
+pub enum Shape {
+    Rect { x: f32, y: f32, width: f32, height: f32 },
+    Ellipse { center_x: f32, center_y: f32, radius_x: f32, radius_y: f32 },
+    Point { x: f32, y: f32 },
+    /// Points offset by the object's position.
+    Polygon(Vec<(f32, f32)>),
+    /// Points offset by the object's position.
+    Polyline(Vec<(f32, f32)>),
+    /// Bounds of a text object.
+    Text { x: f32, y: f32, width: f32, height: f32 },
+}
+
 fn parse_points(points: &str, result: &mut Vec<(f32, f32)>) -> Result<()> {
     let points = points.split(" ");
     for point in points {
@@ -178,8 +405,23 @@ fn parse_points(points: &str, result: &mut Vec<(f32, f32)>) -> Result<()> {
     Ok(())
 }
 
+fn parse_json_points(points: &Value) -> Result<Vec<(f32, f32)>> {
+    let mut result = Vec::new();
+    for point in json_array(points)? {
+        let point = json_object(point)?;
+        let x = json_f32(point.get("x").ok_or(Error::JsonParsingError)?)?;
+        let y = json_f32(point.get("y").ok_or(Error::JsonParsingError)?)?;
+        result.push((x, y));
+    }
+    Ok(result)
+}
+
+fn points_to_json(points: &[(f32, f32)]) -> Value {
+    points.iter().map(|(x, y)| json!({ "x": x, "y": y })).collect()
+}
+
 /// A text object.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
 pub struct Text {
     value: String,
     font_family: Option<String>,
@@ -218,7 +460,7 @@ impl Text {
     pub fn value(&self) -> &str { &self.value }
     pub fn font_family(&self) -> &str {
         match &self.font_family {
-            Some(font_family) => &font_family,
+            Some(font_family) => font_family,
             None => "sans-serif",
         }
     }
@@ -256,6 +498,47 @@ impl Text {
         }
         Ok(result)
     }
+
+    pub(crate) fn parse_json(text_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(text_value)? {
+            match name.as_str() {
+                "text" => result.value = json_str(value)?.into(),
+                "fontfamily" => result.font_family = Some(json_str(value)?.into()),
+                "pixelsize" => result.pixel_size = json_f32(value)?,
+                "wrap" => result.wrap = json_bool(value)?,
+                "color" => result.color = json_str(value)?.parse()?,
+                "bold" => result.bold = json_bool(value)?,
+                "italic" => result.italic = json_bool(value)?,
+                "underline" => result.underline = json_bool(value)?,
+                "strikeout" => result.strikeout = json_bool(value)?,
+                "kerning" => result.kerning = json_bool(value)?,
+                "halign" => result.halign = json_str(value)?.parse()?,
+                "valign" => result.valign = json_str(value)?.parse()?,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("text".into(), self.value.as_str().into());
+        if let Some(font_family) = &self.font_family {
+            object.insert("fontfamily".into(), font_family.as_str().into());
+        }
+        object.insert("pixelsize".into(), self.pixel_size.into());
+        object.insert("wrap".into(), self.wrap.into());
+        object.insert("color".into(), self.color.to_string().into());
+        object.insert("bold".into(), self.bold.into());
+        object.insert("italic".into(), self.italic.into());
+        object.insert("underline".into(), self.underline.into());
+        object.insert("strikeout".into(), self.strikeout.into());
+        object.insert("kerning".into(), self.kerning.into());
+        object.insert("halign".into(), self.halign.as_str().into());
+        object.insert("valign".into(), self.valign.as_str().into());
+        Value::Object(object)
+    }
 }
 
 /// Horizontal alignment of text.
@@ -281,6 +564,21 @@ impl FromStr for HAlign {
     }
 }
 
+impl HAlign {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+            Self::Justify => "justify",
+        }
+    }
+}
+
 /// Vertical alignment of text.
 #[derive(Copy, Clone, Eq, PartialEq, Hash, PartialOrd, Ord, Default, Debug)]
 pub enum VAlign {
@@ -300,4 +598,310 @@ impl FromStr for VAlign {
             _ => Err(Error::ParsingError),
         }
     }
-}
\ No newline at end of file
+}
+
+impl VAlign {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Top => "top",
+            Self::Center => "center",
+            Self::Bottom => "bottom",
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use crate::{Error, Map, ObjectKind, PropertyValue, Shape};
+
+    #[test]
+    fn test_objects_in_draw_order() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="topdown" draworder="topdown">
+                    <object id="1" x="0" y="50"/>
+                    <object id="2" x="0" y="-10"/>
+                    <object id="3" x="0" y="20"/>
+                </objectgroup>
+                <objectgroup id="2" name="index" draworder="index">
+                    <object id="1" x="0" y="50"/>
+                    <object id="2" x="0" y="-10"/>
+                    <object id="3" x="0" y="20"/>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+
+        let topdown = map.layers()[0].as_object_group_layer().unwrap();
+        let ids: Vec<u32> = topdown.objects_in_draw_order().iter().map(|object| object.id()).collect();
+        assert_eq!(vec![2, 3, 1], ids);
+
+        let index = map.layers()[1].as_object_group_layer().unwrap();
+        let ids: Vec<u32> = index.objects_in_draw_order().iter().map(|object| object.id()).collect();
+        assert_eq!(vec![1, 2, 3], ids);
+    }
+
+    #[test]
+    fn test_object_class() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" type="spawn" x="0" y="0"/>
+                    <object id="2" class="door" x="0" y="0"/>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let objects = map.layers()[0].as_object_group_layer().unwrap().objects();
+        assert_eq!("spawn", objects[0].typ());
+        assert_eq!("door", objects[1].typ());
+    }
+
+    #[test]
+    fn test_position_and_size() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" x="10" y="20.5" width="30" height="40"/>
+                    <object id="2" x="5" y="6" width="1" height="1"><point/></object>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let objects = map.layers()[0].as_object_group_layer().unwrap().objects();
+        assert_eq!((10.0, 20.5), objects[0].position());
+        assert_eq!((30.0, 40.0), objects[0].size());
+        assert_eq!((5.0, 6.0), objects[1].position());
+        assert_eq!((0.0, 0.0), objects[1].size());
+    }
+
+    #[test]
+    fn test_invalid_gid() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" gid="tree" x="0" y="0"/>
+                </objectgroup>
+            </map>
+        "#;
+        let error = Map::parse_str(xml).unwrap_err();
+        assert!(matches!(&error, Error::InvalidGidError(gid) if gid == "tree"));
+        assert_eq!("Invalid gid 'tree'", error.to_string());
+
+        let json = r#"{ "layers": [{ "type": "objectgroup", "objects": [{ "id": 1, "gid": -5 }] }] }"#;
+        let error = Map::parse_json_str(json).unwrap_err();
+        assert!(matches!(&error, Error::InvalidGidError(gid) if gid == "-5"));
+    }
+
+    #[test]
+    fn test_comma_decimal() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" x="1,5" y="0"/>
+                </objectgroup>
+            </map>
+        "#;
+        let error = Map::parse_str(xml).unwrap_err();
+        assert!(matches!(&error, Error::CommaDecimalError(value) if value == "1,5"));
+
+        let xml = xml.replace("1,5", "1.5.2");
+        let error = Map::parse_str(&xml).unwrap_err();
+        assert!(matches!(error, Error::ParseFloatError(_)));
+
+        let xml = xml.replace("1.5.2", "1.5");
+        let map = Map::parse_str(&xml).unwrap();
+        assert_eq!(1.5, map.layers()[0].as_object_group_layer().unwrap().objects()[0].x());
+    }
+
+    #[test]
+    fn test_class_property() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" x="0" y="0">
+                        <properties>
+                            <property name="stats" type="class" propertytype="Stats">
+                                <properties>
+                                    <property name="health" type="int" value="10"/>
+                                    <property name="weapon" type="class" propertytype="Weapon">
+                                        <properties>
+                                            <property name="name" value="sword"/>
+                                        </properties>
+                                    </property>
+                                </properties>
+                            </property>
+                        </properties>
+                    </object>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let object = &map.layers()[0].as_object_group_layer().unwrap().objects()[0];
+        let stats = object.properties().get("stats").unwrap().as_class().unwrap();
+        assert_eq!(Some(10), stats.get("health").unwrap().as_int());
+        let weapon = stats.get("weapon").unwrap().as_class().unwrap();
+        assert_eq!(Some("sword"), weapon.get("name").unwrap().as_string());
+
+        let json = r#"{
+            "layers": [{
+                "type": "objectgroup",
+                "objects": [{
+                    "id": 1,
+                    "properties": [{
+                        "name": "stats",
+                        "type": "class",
+                        "propertytype": "Stats",
+                        "value": { "health": 10, "speed": 1.5, "weapon": { "name": "sword" } }
+                    }]
+                }]
+            }]
+        }"#;
+        let map = Map::parse_json_str(json).unwrap();
+        let object = &map.layers()[0].as_object_group_layer().unwrap().objects()[0];
+        let stats = object.properties().get("stats").unwrap().as_class().unwrap();
+        assert_eq!(Some(10), stats.get("health").unwrap().as_int());
+        assert_eq!(Some(1.5), stats.get("speed").unwrap().as_float());
+        let weapon = stats.get("weapon").unwrap().as_class().unwrap();
+        assert_eq!(Some("sword"), weapon.get("name").unwrap().as_string());
+    }
+
+    #[test]
+    fn test_kind_predicates() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" x="0" y="0" width="10" height="10"/>
+                    <object id="2" x="0" y="0"><point/></object>
+                    <object id="3" x="0" y="0" width="10" height="10"><ellipse/></object>
+                    <object id="4" x="0" y="0"><polyline points="0,0 10,0"/></object>
+                    <object id="5" x="0" y="0"><polygon points="0,0 10,0 10,10"/></object>
+                    <object id="6" x="0" y="0" width="10" height="10"><text>Hello</text></object>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let objects = map.layers()[0].as_object_group_layer().unwrap().objects();
+        let predicates = |index: usize| {
+            let object = &objects[index];
+            [object.is_rectangle(), object.is_point(), object.is_ellipse(), object.is_polyline(), object.is_polygon(), object.is_text()]
+        };
+        for index in 0..objects.len() {
+            let mut expected = [false; 6];
+            expected[index] = true;
+            assert_eq!(expected, predicates(index));
+        }
+    }
+
+    #[test]
+    fn test_effective_size() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <tileset firstgid="1" name="tiles" tilewidth="16" tileheight="24" tilecount="4" columns="2">
+                    <image source="tiles.png" width="32" height="48"/>
+                </tileset>
+                <objectgroup id="1" name="objects">
+                    <object id="1" gid="2" x="0" y="0"/>
+                    <object id="2" gid="2" x="0" y="0" width="32"/>
+                    <object id="3" gid="2" x="0" y="0" width="8" height="8"/>
+                    <object id="4" x="0" y="0"/>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let objects = map.layers()[0].as_object_group_layer().unwrap().objects();
+        assert_eq!((0.0, 0.0), objects[0].size());
+        assert_eq!((16.0, 24.0), objects[0].effective_size(&map));
+        assert_eq!((32.0, 24.0), objects[1].effective_size(&map));
+        assert_eq!((8.0, 8.0), objects[2].effective_size(&map));
+        assert_eq!((0.0, 0.0), objects[3].effective_size(&map));
+    }
+
+    #[test]
+    fn test_shape() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" x="10" y="20" width="40" height="30"><ellipse/></object>
+                    <object id="2" x="10" y="20"><polygon points="0,0 10,0 10,-5"/></object>
+                    <object id="3" x="10" y="20" width="4" height="3"/>
+                    <object id="4" x="10" y="20"><point/></object>
+                    <object id="5" gid="1" x="10" y="32" width="16" height="16"/>
+                </objectgroup>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let objects = map.layers()[0].as_object_group_layer().unwrap().objects();
+        let expected = Shape::Ellipse { center_x: 30.0, center_y: 35.0, radius_x: 20.0, radius_y: 15.0 };
+        assert_eq!(expected, objects[0].shape());
+        assert_eq!(Shape::Polygon(vec![(10.0, 20.0), (20.0, 20.0), (20.0, 15.0)]), objects[1].shape());
+        assert_eq!(Shape::Rect { x: 10.0, y: 20.0, width: 4.0, height: 3.0 }, objects[2].shape());
+        assert_eq!(Shape::Point { x: 10.0, y: 20.0 }, objects[3].shape());
+        assert_eq!(Shape::Rect { x: 10.0, y: 16.0, width: 16.0, height: 16.0 }, objects[4].shape());
+        assert_eq!((10.0, 16.0, 26.0, 32.0), objects[4].bounds());
+    }
+
+    #[test]
+    fn test_objects_mut() {
+        let mut map = Map::parse_str(include_str!("test_data/finite.tmx")).unwrap();
+        let count = map.all_layers()
+            .filter_map(|layer| layer.as_object_group_layer())
+            .map(|object_group| object_group.objects().len())
+            .sum::<usize>();
+        assert_eq!(count, map.objects_mut().count());
+
+        let object = map.objects_mut().next().unwrap();
+        let id = object.id();
+        object.set_position(12.5, -3.0);
+        object.properties_mut().set("spawn", PropertyValue::Bool(true));
+
+        let object = map.all_layers()
+            .filter_map(|layer| layer.as_object_group_layer())
+            .flat_map(|object_group| object_group.objects())
+            .find(|object| object.id() == id)
+            .unwrap();
+        assert_eq!((12.5, -3.0), object.position());
+        assert_eq!(Some(true), object.properties().get_typed::<bool>("spawn"));
+    }
+
+    #[test]
+    fn test_polygon_winding() {
+        let clockwise = ObjectKind::Polygon(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+        assert_eq!(Some(100.0), clockwise.polygon_area());
+        assert_eq!(Some(true), clockwise.is_clockwise());
+
+        let counter_clockwise = ObjectKind::Polygon(vec![(0.0, 0.0), (0.0, 10.0), (10.0, 10.0), (10.0, 0.0)]);
+        assert_eq!(Some(-100.0), counter_clockwise.polygon_area());
+        assert_eq!(Some(false), counter_clockwise.is_clockwise());
+
+        let polyline = ObjectKind::Polyline(vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)]);
+        assert_eq!(None, polyline.polygon_area());
+        assert_eq!(None, ObjectKind::Rectangle.is_clockwise());
+    }
+
+    #[test]
+    fn test_object_types() {
+        let xml = r#"
+            <map orientation="orthogonal" width="10" height="10" tilewidth="20" tileheight="20">
+                <objectgroup id="1" name="objects">
+                    <object id="1" type="spawn" x="0" y="0"/>
+                    <object id="2" class="door" x="0" y="0"/>
+                    <object id="3" x="0" y="0"/>
+                </objectgroup>
+                <group id="2" name="group">
+                    <objectgroup id="3" name="nested">
+                        <object id="4" type="spawn" x="0" y="0"/>
+                    </objectgroup>
+                </group>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let types: Vec<String> = map.object_types().into_iter().collect();
+        assert_eq!(vec!["door", "spawn"], types);
+    }
+}
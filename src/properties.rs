@@ -1,9 +1,11 @@
 use std::collections::{HashMap, hash_map};
+use std::path::{Path, PathBuf};
 use roxmltree::Node;
-use crate::{Color, Result, Error};
+use serde_json::Value;
+use crate::{json_array, json_bool, json_f32, json_i64, json_object, json_str, json_u32, parse_bool, resolve_path, Color, JsonObject, Result, Error};
 
 /// A set of properties.
-#[derive(Clone, Default, Debug)]
+#[derive(Clone, PartialEq, Default, Debug)]
 pub struct Properties(pub(crate) HashMap<String, PropertyValue>);
 impl Properties {
 
@@ -15,32 +17,142 @@ impl Properties {
         self.0.get(name)
     }
 
+    /// Gets a property converted to a given type.
+    /// None if the property is missing or holds a value of another type.
+    pub fn get_typed<T: FromProperty>(&self, name: &str) -> Option<T> {
+        T::from_property(self.get(name)?)
+    }
+
+    /// Sets a property, replacing any existing property with the same name.
+    pub fn set(&mut self, name: impl Into<String>, value: PropertyValue) {
+        self.0.insert(name.into(), value);
+    }
+
     pub fn contains(&self, name: &str) -> bool {
         self.0.contains_key(name)
     }
 
+    /// Iterates over string properties.
+    pub fn strings(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_string()?)))
+    }
+
+    /// Iterates over int properties that fit in an `i32`.
+    pub fn ints(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_int()?)))
+    }
+
+    /// Iterates over float properties.
+    pub fn floats(&self) -> impl Iterator<Item = (&str, f32)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_float()?)))
+    }
+
+    /// Iterates over bool properties.
+    pub fn bools(&self) -> impl Iterator<Item = (&str, bool)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_bool()?)))
+    }
+
+    /// Iterates over color properties.
+    pub fn colors(&self) -> impl Iterator<Item = (&str, Color)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_color()?)))
+    }
+
+    /// Iterates over file properties.
+    pub fn files(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.iter().filter_map(|(name, value)| Some((name, value.as_file()?)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// True if both sets hold the same properties, regardless of the order they were defined in.
+    /// Members of class properties are compared the same way.
+    pub fn eq_ignoring_order(&self, other: &Properties) -> bool {
+        self.len() == other.len() && self.iter().all(|(name, value)| {
+            other.get(name).is_some_and(|other_value| value.eq_ignoring_order(other_value))
+        })
+    }
+
+    /// Adds all properties from another set, replacing those with the same name.
+    pub(crate) fn merge(&mut self, other: Properties) {
+        self.0.extend(other.0);
+    }
+
     pub(crate) fn parse(properties_node: Node) -> Result<Self> {
         let mut result = Self::default();
         for child_node in properties_node.children() {
-            let name = child_node.tag_name().name();
-            match name {
-                "property" => result.parse_property(child_node)?,
-                _ => {},
+            if child_node.tag_name().name() == "property" {
+                result.parse_property(child_node)?;
             }
         }
         Ok(result)
     }
 
+    pub(crate) fn parse_json(properties_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for property_value in json_array(properties_value)? {
+            let property_object = json_object(property_value)?;
+            let name = match property_object.get("name") {
+                Some(name) => json_str(name)?,
+                None => return Err(Error::JsonParsingError),
+            };
+            let value = match property_object.get("value") {
+                Some(value) => value,
+                None => return Err(Error::JsonParsingError),
+            };
+            let str_type = match property_object.get("type") {
+                Some(str_type) => Some(json_str(str_type)?),
+                None => None,
+            };
+            let value = PropertyValue::parse_json(value, str_type)?;
+            result.0.insert(name.into(), value);
+        }
+        Ok(result)
+    }
+
+    /// Properties as a JSON array, sorted by name so the output is stable.
+    pub(crate) fn to_json(&self) -> Value {
+        let mut names: Vec<&String> = self.0.keys().collect();
+        names.sort();
+        names
+            .into_iter()
+            .map(|name| {
+                let value = &self.0[name];
+                let mut property = JsonObject::new();
+                property.insert("name".into(), name.as_str().into());
+                property.insert("type".into(), value.type_name().into());
+                property.insert("value".into(), value.to_json());
+                Value::Object(property)
+            })
+            .collect()
+    }
+
     fn parse_property(&mut self, property_node: Node) -> Result<()> {
         let name = match property_node.attribute("name") {
             Some(name) => name,
             None => return Err(Error::ParsingError),
         };
+        let str_type = property_node.attribute("type");
+
+        // Class values are nested properties rather than a value attribute.
+        if str_type == Some("class") {
+            let mut members = Properties::default();
+            for child in property_node.children().filter(|child| child.has_tag_name("properties")) {
+                members.merge(Properties::parse(child)?);
+            }
+            self.0.insert(name.into(), PropertyValue::Class(members));
+            return Ok(());
+        }
+
         let str_value = match property_node.attribute("value") {
             Some(value) => value,
             None => return Err(Error::ParsingError),
         };
-        let str_type = property_node.attribute("type");
         let value = PropertyValue::parse(str_value, str_type)?;
         self.0.insert(name.into(), value);
         Ok(())
@@ -59,11 +171,16 @@ impl<'a> IntoIterator for &'a Properties {
 #[derive(Clone, PartialEq, Debug)]
 pub enum PropertyValue {
     String(String),
-    Int(i32),
+    /// Tiled stores ints with 64 bits, so they may not fit in an `i32`.
+    Int(i64),
     Float(f32),
     Bool(bool),
     Color(Color),
     File(String),
+    /// Id of an object in the same map, or 0 if no object is referenced.
+    Object(u32),
+    /// Members of a custom class.
+    Class(Properties),
 }
 
 impl PropertyValue {
@@ -73,20 +190,95 @@ impl PropertyValue {
             Some("string") | None => Ok(Self::String(value.into())),
             Some("int") => Ok(Self::Int(value.parse()?)),
             Some("float") => Ok(Self::Float(value.parse()?)),
-            Some("bool") => Ok(Self::Bool(value.parse()?)),
+            Some("bool") => Ok(Self::Bool(parse_bool(value)?)),
             Some("color") => Ok(Self::Color(value.parse()?)),
             Some("file") => Ok(Self::File(value.into())),
+            Some("object") => Ok(Self::Object(value.parse()?)),
             Some(_) => Err(Error::ParsingError)
         }
     }
 
+    fn parse_json(value: &Value, type_name: Option<&str>) -> Result<Self> {
+        match type_name {
+            Some("string") | None => Ok(Self::String(json_str(value)?.into())),
+            Some("int") => Ok(Self::Int(json_i64(value)?)),
+            Some("float") => Ok(Self::Float(json_f32(value)?)),
+            Some("bool") => Ok(Self::Bool(json_bool(value)?)),
+            Some("color") => Ok(Self::Color(json_str(value)?.parse()?)),
+            Some("file") => Ok(Self::File(json_str(value)?.into())),
+            Some("object") => Ok(Self::Object(json_u32(value)?)),
+            Some("class") => Ok(Self::Class(Self::parse_json_class(value)?)),
+            Some(_) => Err(Error::JsonParsingError)
+        }
+    }
+
+    /// JSON class values map member names to bare values, so member types are inferred.
+    /// Colors and files are indistinguishable from strings, and are read as such.
+    fn parse_json_class(value: &Value) -> Result<Properties> {
+        let mut members = Properties::default();
+        for (name, value) in json_object(value)? {
+            let member = match value {
+                Value::Bool(_) => Self::Bool(json_bool(value)?),
+                Value::Number(number) if number.is_i64() => Self::Int(json_i64(value)?),
+                Value::Number(_) => Self::Float(json_f32(value)?),
+                Value::String(_) => Self::String(json_str(value)?.into()),
+                Value::Object(_) => Self::Class(Self::parse_json_class(value)?),
+                _ => return Err(Error::JsonParsingError),
+            };
+            members.0.insert(name.clone(), member);
+        }
+        Ok(members)
+    }
+
+    fn eq_ignoring_order(&self, other: &PropertyValue) -> bool {
+        match (self, other) {
+            (Self::Class(members), Self::Class(other_members)) => members.eq_ignoring_order(other_members),
+            _ => self == other,
+        }
+    }
+
+    /// Name of the value's type as written in Tiled files.
+    fn type_name(&self) -> &'static str {
+        match self {
+            Self::String(_) => "string",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Bool(_) => "bool",
+            Self::Color(_) => "color",
+            Self::File(_) => "file",
+            Self::Object(_) => "object",
+            Self::Class(_) => "class",
+        }
+    }
+
+    /// Class members are written as bare values, mirroring [`parse_json_class`](Self::parse_json_class).
+    fn to_json(&self) -> Value {
+        match self {
+            Self::String(string) | Self::File(string) => string.as_str().into(),
+            Self::Int(int) => (*int).into(),
+            Self::Float(float) => (*float).into(),
+            Self::Bool(bool) => (*bool).into(),
+            Self::Color(color) => color.to_string().into(),
+            Self::Object(id) => (*id).into(),
+            Self::Class(members) => members
+                .iter()
+                .map(|(name, member)| (name.to_string(), member.to_json()))
+                .collect::<JsonObject>()
+                .into(),
+        }
+    }
+
     pub fn as_string(&self) -> Option<&str> {
         match self {
-            PropertyValue::String(str) => Some(&str),
+            PropertyValue::String(str) => Some(str),
             _ => None,
         }
     }
+    /// None if the value is not an int, or does not fit in an `i32`.
     pub fn as_int(&self) -> Option<i32> {
+        i32::try_from(self.as_i64()?).ok()
+    }
+    pub fn as_i64(&self) -> Option<i64> {
         match self {
             PropertyValue::Int(int) => Some(*int),
             _ => None,
@@ -119,10 +311,59 @@ impl PropertyValue {
     }
     pub fn as_file(&self) -> Option<&str> {
         match self {
-            PropertyValue::File(file) => Some(&file),
+            PropertyValue::File(file) => Some(file),
             _ => None,
         }
     }
+    pub fn as_object(&self) -> Option<u32> {
+        match self {
+            PropertyValue::Object(id) => Some(*id),
+            _ => None,
+        }
+    }
+    pub fn as_class(&self) -> Option<&Properties> {
+        match self {
+            PropertyValue::Class(members) => Some(members),
+            _ => None,
+        }
+    }
+
+    /// Path of a file property, joined against the directory it is relative to.
+    /// Absolute paths are returned as-is.
+    /// Both `/` and `\` are treated as separators, so paths saved on any OS can be opened.
+    pub fn as_path(&self, base_dir: &Path) -> Option<PathBuf> {
+        Some(resolve_path(base_dir, self.as_file()?))
+    }
+}
+
+/// A type that can be read from a [`PropertyValue`] of matching type.
+/// See [`Properties::get_typed`].
+pub trait FromProperty: Sized {
+    fn from_property(value: &PropertyValue) -> Option<Self>;
+}
+
+impl FromProperty for i32 {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_int() }
+}
+
+impl FromProperty for i64 {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_i64() }
+}
+
+impl FromProperty for f32 {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_float() }
+}
+
+impl FromProperty for bool {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_bool() }
+}
+
+impl FromProperty for String {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_string().map(String::from) }
+}
+
+impl FromProperty for Color {
+    fn from_property(value: &PropertyValue) -> Option<Self> { value.as_color() }
 }
 
 /// An iterator over a [`Properties`] object.
@@ -141,9 +382,11 @@ impl<'a> Iterator for Props<'a> {
 #[cfg(test)]
 mod test {
     use std::collections::HashMap;
-    use crate::{Properties, PropertyValue};
+    use std::path::{Path, PathBuf};
+    use crate::{Color, Map, Properties, PropertyValue, Tileset};
 
     #[test]
+    #[allow(clippy::approx_constant)]
     fn test_properties() {
         let mut properties: HashMap<String, PropertyValue> = HashMap::new();
         properties.insert("steve".into(), PropertyValue::Bool(true));
@@ -164,4 +407,174 @@ mod test {
         assert_eq!(Some(&PropertyValue::Float(3.14)), properties.get("sarah"));
         assert_eq!(None, properties.get("samuel"));
     }
+
+    #[test]
+    fn test_properties_len() {
+        let xml = include_str!("test_data/tilesets/shape.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        let empty = tileset.tile(0).unwrap().properties();
+        assert!(empty.is_empty());
+        assert_eq!(0, empty.len());
+        let several = tileset.tile(72).unwrap().properties();
+        assert!(!several.is_empty());
+        assert_eq!(2, several.len());
+    }
+
+    #[test]
+    fn test_properties_of_type() {
+        let mut properties: HashMap<String, PropertyValue> = HashMap::new();
+        properties.insert("music".into(), PropertyValue::File("music.ogg".into()));
+        properties.insert("sprite".into(), PropertyValue::File("sprite.png".into()));
+        properties.insert("name".into(), PropertyValue::String("steve".into()));
+        properties.insert("hp".into(), PropertyValue::Int(10));
+        properties.insert("tint".into(), PropertyValue::Color(Color::WHITE));
+        let properties = Properties(properties);
+
+        let mut files: Vec<(&str, &str)> = properties.files().collect();
+        files.sort();
+        assert_eq!(vec![("music", "music.ogg"), ("sprite", "sprite.png")], files);
+        assert_eq!(vec![("hp", 10)], properties.ints().collect::<Vec<_>>());
+        assert_eq!(vec![("name", "steve")], properties.strings().collect::<Vec<_>>());
+        assert_eq!(vec![("tint", Color::WHITE)], properties.colors().collect::<Vec<_>>());
+        assert_eq!(0, properties.floats().count());
+        assert_eq!(0, properties.bools().count());
+    }
+
+    #[test]
+    fn test_as_path() {
+        let xml = r#"
+            <properties>
+                <property name="sprite" type="file" value="..\sprites/hero.png"/>
+                <property name="name" value="hero"/>
+            </properties>
+        "#;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let properties = Properties::parse(doc.root_element()).unwrap();
+        let base_dir = Path::new("assets").join("maps");
+        let expected = base_dir.join("..").join("sprites").join("hero.png");
+        assert_eq!(Some(expected), properties.get("sprite").unwrap().as_path(&base_dir));
+        assert_eq!(None, properties.get("name").unwrap().as_path(&base_dir));
+
+        for absolute in [r"C:\assets\hero.png", "d:/assets/hero.png", r"\\server\share\hero.png", "/assets/hero.png"] {
+            let file = PropertyValue::File(absolute.into());
+            assert_eq!(Some(PathBuf::from(absolute)), file.as_path(&base_dir));
+        }
+    }
+
+    #[test]
+    fn test_repeated_properties() {
+        let xml = r#"
+            <map orientation="orthogonal" width="1" height="1" tilewidth="20" tileheight="20">
+                <properties>
+                    <property name="name" value="first"/>
+                    <property name="first_only" type="int" value="1"/>
+                </properties>
+                <properties>
+                    <property name="name" value="second"/>
+                    <property name="second_only" type="bool" value="true"/>
+                </properties>
+            </map>
+        "#;
+        let map = Map::parse_str(xml).unwrap();
+        let properties = map.properties();
+        assert_eq!(3, properties.len());
+        assert_eq!(Some("second"), properties.get("name").unwrap().as_string());
+        assert_eq!(Some(1), properties.get("first_only").unwrap().as_int());
+        assert_eq!(Some(true), properties.get("second_only").unwrap().as_bool());
+    }
+
+    #[test]
+    fn test_get_typed() {
+        let mut properties: HashMap<String, PropertyValue> = HashMap::new();
+        properties.insert("hp".into(), PropertyValue::Int(10));
+        properties.insert("speed".into(), PropertyValue::Float(1.5));
+        properties.insert("alive".into(), PropertyValue::Bool(true));
+        properties.insert("name".into(), PropertyValue::String("steve".into()));
+        properties.insert("tint".into(), PropertyValue::Color(Color::WHITE));
+        let properties = Properties(properties);
+
+        assert_eq!(Some(10), properties.get_typed::<i32>("hp"));
+        assert_eq!(Some(1.5), properties.get_typed::<f32>("speed"));
+        assert_eq!(Some(true), properties.get_typed::<bool>("alive"));
+        assert_eq!(Some(String::from("steve")), properties.get_typed::<String>("name"));
+        assert_eq!(Some(Color::WHITE), properties.get_typed::<Color>("tint"));
+
+        assert_eq!(None, properties.get_typed::<f32>("hp"));
+        assert_eq!(None, properties.get_typed::<String>("tint"));
+        assert_eq!(None, properties.get_typed::<i32>("missing"));
+    }
+
+    #[test]
+    fn test_large_int() {
+        let xml = r#"
+            <properties>
+                <property name="big" type="int" value="5000000000"/>
+                <property name="small" type="int" value="-7"/>
+            </properties>
+        "#;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let properties = Properties::parse(doc.root_element()).unwrap();
+        let big = properties.get("big").unwrap();
+        assert_eq!(Some(5_000_000_000), big.as_i64());
+        assert_eq!(None, big.as_int());
+        assert_eq!(Some(5e9), big.as_number());
+        assert_eq!(Some(5_000_000_000), properties.get_typed::<i64>("big"));
+        assert_eq!(None, properties.get_typed::<i32>("big"));
+        assert_eq!(Some(-7), properties.get("small").unwrap().as_int());
+        assert_eq!(vec![("small", -7)], properties.ints().collect::<Vec<_>>());
+
+        let json = r#"[{ "name": "big", "type": "int", "value": 5000000000 }]"#;
+        let properties = Properties::parse_json(&serde_json::from_str(json).unwrap()).unwrap();
+        assert_eq!(Some(5_000_000_000), properties.get_typed::<i64>("big"));
+    }
+
+    #[test]
+    fn test_eq_ignoring_order() {
+        let mut members = Properties::default();
+        members.set("speed", PropertyValue::Float(1.5));
+        members.set("alive", PropertyValue::Bool(true));
+        let mut reversed_members = Properties::default();
+        reversed_members.set("alive", PropertyValue::Bool(true));
+        reversed_members.set("speed", PropertyValue::Float(1.5));
+
+        let mut properties = Properties::default();
+        properties.set("name", PropertyValue::String("hero".into()));
+        properties.set("health", PropertyValue::Int(10));
+        properties.set("stats", PropertyValue::Class(members));
+        let mut reversed = Properties::default();
+        reversed.set("stats", PropertyValue::Class(reversed_members));
+        reversed.set("health", PropertyValue::Int(10));
+        reversed.set("name", PropertyValue::String("hero".into()));
+
+        assert_eq!(properties, reversed);
+        assert!(properties.eq_ignoring_order(&reversed));
+        assert!(reversed.eq_ignoring_order(&properties));
+
+        reversed.set("health", PropertyValue::Int(11));
+        assert_ne!(properties, reversed);
+        assert!(!properties.eq_ignoring_order(&reversed));
+        reversed.set("health", PropertyValue::Int(10));
+        reversed.set("extra", PropertyValue::Bool(false));
+        assert!(!properties.eq_ignoring_order(&reversed));
+        assert!(!reversed.eq_ignoring_order(&properties));
+    }
+
+    #[test]
+    fn test_object_property() {
+        let xml = r#"
+            <properties>
+                <property name="target" type="object" value="12"/>
+                <property name="unset" type="object" value="0"/>
+            </properties>
+        "#;
+        let doc = roxmltree::Document::parse(xml).unwrap();
+        let properties = Properties::parse(doc.root_element()).unwrap();
+        assert_eq!(Some(12), properties.get("target").unwrap().as_object());
+        assert_eq!(Some(0), properties.get("unset").unwrap().as_object());
+
+        let json = r#"[{ "name": "target", "type": "object", "value": 12 }]"#;
+        let properties = Properties::parse_json(&serde_json::from_str(json).unwrap()).unwrap();
+        assert_eq!(Some(&PropertyValue::Object(12)), properties.get("target"));
+        assert_eq!(json.replace(' ', ""), properties.to_json().to_string());
+    }
 }
\ No newline at end of file
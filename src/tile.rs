@@ -1,10 +1,13 @@
+use std::fmt;
 use roxmltree::Node;
-use crate::{Image, ObjectGroupLayer, Properties, Result};
+use serde_json::{json, Value};
+use crate::{json_array, json_object, json_str, json_u32, Image, JsonObject, ObjectGroupLayer, Properties, Result, Tileset};
 
 
 /// A tile belonging to a [`Tileset`](crate::Tileset).
 #[derive(Clone, Default, Debug)]
 pub struct Tile {
+    id: u32,
     typ: String,
     properties: Properties,
     image: Option<Image>,
@@ -17,7 +20,13 @@ pub struct Tile {
 }
 
 impl Tile {
+    /// Local id of the tile within its tileset.
+    pub fn id(&self) -> u32 { self.id }
+
+    /// Class of the tile.
+    /// Read from the `class` attribute, or `type` in files saved before Tiled 1.9.
     pub fn typ(&self) -> &str { &self.typ }
+
     pub fn properties(&self) -> &Properties { &self.properties }
     pub fn image(&self) -> Option<&Image> { self.image.as_ref() }
     pub fn x(&self) -> Option<u32> { self.x }
@@ -25,8 +34,48 @@ impl Tile {
     pub fn width(&self) -> Option<u32> { self.width }
     pub fn height(&self) -> Option<u32> { self.height }
     pub fn animation(&self) -> Option<&Animation> { self.animation.as_ref() }
+    pub fn animation_mut(&mut self) -> Option<&mut Animation> { self.animation.as_mut() }
     pub fn objects(&self) -> Option<&ObjectGroupLayer> { self.objects.as_ref() }
 
+    /// Smallest rectangle containing all collision objects of the tile, in pixels relative to the tile's top-left corner.
+    /// Returned as (x, y, width, height). Object rotation is not applied.
+    /// None if the tile has no collision objects.
+    pub fn collision_aabb(&self) -> Option<(f32, f32, f32, f32)> {
+        let (min_x, min_y, max_x, max_y) = self.objects()?
+            .objects()
+            .iter()
+            .map(|object| object.bounds())
+            .reduce(|(a_min_x, a_min_y, a_max_x, a_max_y), (b_min_x, b_min_y, b_max_x, b_max_y)| {
+                (a_min_x.min(b_min_x), a_min_y.min(b_min_y), a_max_x.max(b_max_x), a_max_y.max(b_max_y))
+            })?;
+        if min_x > max_x || min_y > max_y {
+            return None;
+        }
+        Some((min_x, min_y, max_x - min_x, max_y - min_y))
+    }
+
+    /// True if the tile has its own image, as in image collection tilesets.
+    /// Tiles in single-image tilesets are drawn from the tileset's image instead.
+    pub fn has_own_image(&self) -> bool { self.image.is_some() }
+
+    /// Size of the tile in pixels, given the tileset it belongs to.
+    /// Uses the tile's own size if set, then the size of its own image, then the tileset's tile size.
+    pub fn effective_size(&self, tileset: &Tileset) -> (u32, u32) {
+        let image = self.image.as_ref();
+        let width = self.width
+            .or_else(|| image.and_then(|image| image.width()))
+            .unwrap_or(tileset.tile_width());
+        let height = self.height
+            .or_else(|| image.and_then(|image| image.height()))
+            .unwrap_or(tileset.tile_height());
+        (width, height)
+    }
+
+    /// Tile with no data other than its id.
+    pub(crate) fn with_id(id: u32) -> Self {
+        Self { id, ..Self::default() }
+    }
+
     pub(crate) fn parse(tile_node: Node) -> Result<(u32, Tile)> {
 
         // Attributes
@@ -35,6 +84,7 @@ impl Tile {
         for attr in tile_node.attributes() {
             match attr.name() {
                 "id" => id = attr.value().parse()?,
+                "type" | "class" => result.typ = attr.value().into(),
                 "x" => result.x = Some(attr.value().parse()?),
                 "y" => result.y = Some(attr.value().parse()?),
                 "width" => result.width = Some(attr.value().parse()?),
@@ -46,7 +96,7 @@ impl Tile {
         // Children
         for child in tile_node.children() {
             match child.tag_name().name() {
-                "properties" => result.properties = Properties::parse(child)?,
+                "properties" => result.properties.merge(Properties::parse(child)?),
                 "image" => result.image = Some(Image::parse(child)?),
                 "animation" => result.animation = Some(Animation::parse(child)?),
                 "objectgroup" => result.objects = Some(ObjectGroupLayer::parse(child)?),
@@ -54,8 +104,77 @@ impl Tile {
             }
         }
 
+        result.id = id;
         Ok((id, result))
     }
+
+    pub(crate) fn parse_json(tile_value: &Value) -> Result<(u32, Tile)> {
+        let tile_object = json_object(tile_value)?;
+        let mut id = 0;
+        let mut result = Self {
+            image: Image::parse_json(tile_object)?,
+            ..Self::default()
+        };
+        for (name, value) in tile_object {
+            match name.as_str() {
+                "id" => id = json_u32(value)?,
+                "type" | "class" => result.typ = json_str(value)?.into(),
+                "x" => result.x = Some(json_u32(value)?),
+                "y" => result.y = Some(json_u32(value)?),
+                "width" => result.width = Some(json_u32(value)?),
+                "height" => result.height = Some(json_u32(value)?),
+                "properties" => result.properties = Properties::parse_json(value)?,
+                "animation" => result.animation = Some(Animation::parse_json(value)?),
+                "objectgroup" => result.objects = Some(ObjectGroupLayer::parse_json(json_object(value)?)?),
+                _ => {}
+            }
+        }
+        result.id = id;
+        Ok((id, result))
+    }
+
+    /// True if the tile has no data besides its id, like most tiles of single-image tilesets.
+    /// Tiled does not write such tiles.
+    pub(crate) fn is_plain(&self) -> bool {
+        self.typ.is_empty()
+            && self.properties.is_empty()
+            && self.image.is_none()
+            && self.animation.is_none()
+            && self.x.is_none()
+            && self.y.is_none()
+            && self.width.is_none()
+            && self.height.is_none()
+            && self.objects.is_none()
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("id".into(), self.id.into());
+        if !self.typ.is_empty() {
+            object.insert("type".into(), self.typ.as_str().into());
+        }
+        if let Some(image) = &self.image {
+            image.write_json(&mut object);
+        }
+        for (name, value) in [("x", self.x), ("y", self.y), ("width", self.width), ("height", self.height)] {
+            if let Some(value) = value {
+                object.insert(name.into(), value.into());
+            }
+        }
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        if let Some(animation) = &self.animation {
+            object.insert("animation".into(), animation.to_json());
+        }
+        if let Some(objects) = &self.objects {
+            let mut objects_object = JsonObject::new();
+            objects_object.insert("type".into(), "objectgroup".into());
+            objects.write_json(&mut objects_object);
+            object.insert("objectgroup".into(), objects_object.into());
+        }
+        Value::Object(object)
+    }
 }
 
 /// Global id of a tile in a [`Map`](crate::Map).
@@ -90,6 +209,52 @@ impl Gid {
     pub fn is_rotated_hex_120(self) -> bool {
         self.0 & Self::ROTATED_HEXAGONAL_120_FLAG != 0
     }
+
+    /// Flip/rotation information of the gid.
+    pub fn transform(self) -> Transform {
+        Transform {
+            flip_horizontally: self.is_flipped_horizontally(),
+            flip_vertically: self.is_flipped_vertically(),
+            flip_diagonally: self.is_flipped_diagonally(),
+            rotate_hex_120: self.is_rotated_hex_120(),
+        }
+    }
+}
+
+impl From<u32> for Gid {
+    fn from(value: u32) -> Self { Self(value) }
+}
+
+impl From<Gid> for u32 {
+    fn from(gid: Gid) -> Self { gid.0 }
+}
+
+/// Displays the gid's value, followed by its flags if any are set.
+/// Flags are abbreviated as H, V and D for the flips, and R for hexagonal rotation, like "1 [HD]".
+impl fmt::Display for Gid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.value())?;
+        let flags = [
+            (self.is_flipped_horizontally(), 'H'),
+            (self.is_flipped_vertically(), 'V'),
+            (self.is_flipped_diagonally(), 'D'),
+            (self.is_rotated_hex_120(), 'R'),
+        ];
+        if flags.iter().any(|(set, _)| *set) {
+            let flags: String = flags.iter().filter(|(set, _)| *set).map(|(_, flag)| flag).collect();
+            write!(f, " [{flags}]")?;
+        }
+        Ok(())
+    }
+}
+
+/// Flip/rotation information stored in the upper bits of a [`Gid`].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Default, Debug)]
+pub struct Transform {
+    pub flip_horizontally: bool,
+    pub flip_vertically: bool,
+    pub flip_diagonally: bool,
+    pub rotate_hex_120: bool,
 }
 
 /// Animation frames of a [`Tile`].
@@ -99,6 +264,9 @@ impl Animation {
     
     pub fn frames(&self) -> &[Frame] { &self.0 }
 
+    /// Frames for editing, such as to change their durations or order.
+    pub fn frames_mut(&mut self) -> &mut Vec<Frame> { &mut self.0 }
+
     pub(crate) fn parse(animation_node: Node) -> Result<Self> {
         let mut frames = Vec::new();
         for frame_node in animation_node.children().filter(|node| node.tag_name().name() == "frame") {
@@ -114,6 +282,29 @@ impl Animation {
         }
         Ok(Self(frames))
     }
+
+    pub(crate) fn to_json(&self) -> Value {
+        self.0
+            .iter()
+            .map(|frame| json!({ "tileid": frame.tile_id, "duration": frame.duration }))
+            .collect()
+    }
+
+    pub(crate) fn parse_json(animation_value: &Value) -> Result<Self> {
+        let mut frames = Vec::new();
+        for frame_value in json_array(animation_value)? {
+            let mut frame = Frame::default();
+            for (name, value) in json_object(frame_value)? {
+                match name.as_str() {
+                    "tileid" => frame.tile_id = json_u32(value)?,
+                    "duration" => frame.duration = json_u32(value)?,
+                    _ => {}
+                }
+            }
+            frames.push(frame);
+        }
+        Ok(Self(frames))
+    }
 }
 
 /// A frame in a tile [`Animation`].
@@ -121,4 +312,62 @@ impl Animation {
 pub struct Frame {
     pub tile_id: u32,
     pub duration: u32,
-}
\ No newline at end of file
+}
+
+impl Frame {
+    /// Global id of the frame's tile, given the first gid of the tileset it belongs to.
+    /// None if the id would overflow into the bits used for flip flags.
+    pub fn gid(&self, first_gid: u32) -> Option<Gid> {
+        first_gid
+            .checked_add(self.tile_id)
+            .filter(|&gid| gid <= Gid::FLIP_MASK)
+            .map(Gid)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Gid, Tileset};
+
+    #[test]
+    fn test_gid_conversions() {
+        let gid: Gid = 2147483746.into();
+        assert_eq!(Gid(2147483746), gid);
+        assert_eq!(2147483746, u32::from(gid));
+    }
+
+    #[test]
+    fn test_gid_display() {
+        assert_eq!("98", Gid(98).to_string());
+        assert_eq!("1 [H]", Gid(1 | Gid::FLIPPED_HORIZONTALLY_FLAG).to_string());
+        let all_flags = Gid::FLIPPED_HORIZONTALLY_FLAG
+            | Gid::FLIPPED_VERTICALLY_FLAG
+            | Gid::FLIPPED_DIAGONALLY_FLAG
+            | Gid::ROTATED_HEXAGONAL_120_FLAG;
+        assert_eq!("5 [HVDR]", Gid(5 | all_flags).to_string());
+        assert_eq!("0", Gid::NULL.to_string());
+    }
+
+    #[test]
+    fn test_collision_aabb() {
+        let tileset = Tileset::parse_str(include_str!("test_data/tilesets/shape.tsx")).unwrap();
+        let (x, y, width, height) = tileset.tile(72).unwrap().collision_aabb().unwrap();
+        assert_eq!((6.28437, 4.27337), (x, y));
+        assert_eq!(8.37916 - 6.28437, width);
+        assert_eq!(12.3174 - 4.27337, height);
+        assert_eq!(None, tileset.tile(76).unwrap().collision_aabb());
+    }
+
+    #[test]
+    fn test_animation_mut() {
+        let mut tileset = Tileset::parse_str(include_str!("test_data/tilesets/vikings_of_midgard.tsx")).unwrap();
+        let frames = tileset.tile_mut(144).unwrap().animation_mut().unwrap().frames_mut();
+        frames[0].duration = 250;
+        frames.swap(0, 3);
+
+        let frames = tileset.tile(144).unwrap().animation().unwrap().frames();
+        assert_eq!(vec![147, 145, 146, 144], frames.iter().map(|frame| frame.tile_id).collect::<Vec<_>>());
+        assert_eq!(vec![100, 100, 100, 250], frames.iter().map(|frame| frame.duration).collect::<Vec<_>>());
+        assert!(tileset.tile_mut(145).unwrap().animation_mut().is_none());
+    }
+}
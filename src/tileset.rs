@@ -1,8 +1,13 @@
+use std::borrow::Cow;
 use std::collections::hash_map::Iter as HashMapIter;
 use std::collections::HashMap;
+use std::fs::File;
+use std::str::FromStr;
 use std::io::Read;
+use std::path::Path;
 use roxmltree::{Document, Node};
-use crate::{Error, Image, Orientation, Properties, Result, Tile, TileOffset};
+use serde_json::{json, Value};
+use crate::{format_extension, json_array, json_object, json_str, json_u32, read_text, strip_bom, Animation, Error, Image, JsonObject, Orientation, Properties, Result, Tile, TileOffset, WangSet};
 
 
 /// A tileset parsed from a tileset file, or a map file when embedded.
@@ -24,6 +29,7 @@ pub struct Tileset {
     grid: Option<Grid>,
     image: Option<Image>,
     tiles: HashMap<u32, Tile>,
+    wang_sets: Vec<WangSet>,
 }
 
 impl Tileset {
@@ -31,6 +37,7 @@ impl Tileset {
     pub fn class(&self) -> &str { &self.class }
     pub fn tile_width(&self) -> u32 { self.tile_width }
     pub fn tile_height(&self) -> u32 { self.tile_height }
+    pub fn tile_size(&self) -> (u32, u32) { (self.tile_width, self.tile_height) }
     pub fn spacing(&self) -> u32 { self.spacing }
     pub fn margin(&self) -> u32 { self.margin }
     pub fn tile_count(&self) -> u32 { self.tile_count }
@@ -42,44 +49,146 @@ impl Tileset {
     pub fn tile_offset(&self) -> TileOffset { self.tile_offset }
     pub fn grid(&self) -> Option<Grid> { self.grid }
     pub fn image(&self) -> Option<&Image> { self.image.as_ref() }
+    pub fn wang_sets(&self) -> &[WangSet] { &self.wang_sets }
+
+    /// Checks that every wang tile refers to a tile in the tileset, and to colors in its wang set.
+    pub fn validate_wangsets(&self) -> Result<()> {
+        for wang_set in &self.wang_sets {
+            let color_count = wang_set.colors().len();
+            for wang_tile in wang_set.wang_tiles() {
+                let tile_exists = self.tiles.contains_key(&wang_tile.tile_id);
+                let colors_exist = wang_tile.wang_id.iter().all(|&color| color as usize <= color_count);
+                if !tile_exists || !colors_exist {
+                    return Err(Error::InvalidWangTileError {
+                        wang_set: wang_set.name().into(),
+                        tile_id: wang_tile.tile_id,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Size of the tileset's image, in pixels.
+    /// Derived from the tile layout when the image does not declare its size.
+    /// None if this is an image collection tileset.
+    pub fn image_dimensions(&self) -> Option<(u32, u32)> {
+        let image = self.image.as_ref()?;
+        if let (Some(width), Some(height)) = (image.width(), image.height()) {
+            return Some((width, height));
+        }
+        if self.columns == 0 {
+            return None;
+        }
+        let rows = self.tile_count.div_ceil(self.columns);
+        let span = |count: u32, size: u32| 2 * self.margin + count * size + count.saturating_sub(1) * self.spacing;
+        Some((span(self.columns, self.tile_width), span(rows, self.tile_height)))
+    }
+
+    /// True if this is an image collection tileset, where each tile has its own image.
+    /// False if all tiles share a single image.
+    pub fn is_collection(&self) -> bool { self.image.is_none() }
+
+    /// Image to sample when drawing a tile.
+    /// That is the tileset's image in single-image tilesets, and the tile's own image in image collections.
+    /// None if no tile has the given id.
+    pub fn tile_image(&self, id: u32) -> Option<&Image> {
+        match &self.image {
+            Some(image) if id < self.tile_count => Some(image),
+            Some(_) => None,
+            None => self.tile(id)?.image(),
+        }
+    }
+
     pub fn tiles(&self) -> Tiles<'_> {
         Tiles {
             iter: self.tiles.iter(),
         }
     }
 
+    /// Iterates over tiles whose type (class) matches.
+    pub fn tiles_of_type<'a>(&'a self, typ: &'a str) -> impl Iterator<Item = (u32, &'a Tile)> + 'a {
+        self.tiles().filter(move |(_, tile)| tile.typ() == typ)
+    }
+
+    /// Iterates over tiles that have an animation, along with their animations.
+    pub fn animated_tiles(&self) -> impl Iterator<Item = (u32, &Animation)> {
+        self.tiles().filter_map(|(id, tile)| Some((id, tile.animation()?)))
+    }
+
     /// Gets a tile using its local id.
     /// None if not found.
     pub fn tile(&self, id: u32) -> Option<&Tile> {
         self.tiles.get(&id)
     }
 
+    /// Gets a tile using its local id, for editing.
+    /// None if not found.
+    pub fn tile_mut(&mut self, id: u32) -> Option<&mut Tile> {
+        self.tiles.get_mut(&id)
+    }
+
+    /// Gets a tile using its local id.
+    /// In single-image tilesets, every id below [`tile_count`](Self::tile_count) is present.
+    /// For ids that are not found, such as out-of-range ids or ids missing from image collections,
+    /// an owned empty [`Tile`] with the requested id is returned instead.
+    pub fn tile_or_default(&self, id: u32) -> Cow<'_, Tile> {
+        match self.tile(id) {
+            Some(tile) => Cow::Borrowed(tile),
+            None => Cow::Owned(Tile::with_id(id)),
+        }
+    }
+
     /// Gets a tile using its x,y coordinates in the tileset.
     /// None if out of bounds.
     /// None if this is an image collection tileset.
     pub fn tile_at(&self, x: u32, y: u32) -> Option<&Tile> {
-        if self.image.is_none() { return None }
+        self.image.as_ref()?;
         if x > self.columns { return None }
         let id = y * self.columns + x;
         self.tile(id)
     }
 
-    pub fn parse(mut read: impl Read) -> Result<Self> {
-        let mut xml_str = String::new();
-        read.read_to_string(&mut xml_str)?;
+    /// Loads a tileset from a file.
+    /// Files ending in `.tsx` are parsed as XML, and files ending in `.tsj` or `.json` as JSON.
+    /// With the `flate2` feature, these may be followed by `.gz` for gzipped files.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = File::open(path)?;
+        match format_extension(path) {
+            Some("tsx") => Self::parse(file),
+            Some("tsj") | Some("json") => Self::parse_json(file),
+            _ => Err(Error::UnsupportedFileError),
+        }
+    }
+
+    /// Parses XML that is either UTF-8, or UTF-16 starting with a byte order mark.
+    /// With the `flate2` feature, gzipped files such as `.tsx.gz` are decompressed first.
+    pub fn parse(read: impl Read) -> Result<Self> {
+        let xml_str = read_text(read)?;
         Self::parse_str(&xml_str)
     }
 
     pub fn parse_str(xml_str: &str) -> Result<Self> {
         let mut result = Tileset::default();
-        let xml_doc = Document::parse(&xml_str)?;
-        let root = xml_doc.root();
-        for node in root.children() {
-            match node.tag_name().name() {
-                "tileset" => result.parse_node(node)?,
-                _ => {}
-            }
+        let xml_doc = Document::parse(strip_bom(xml_str))?;
+        let root = xml_doc.root_element();
+        if root.tag_name().name() != "tileset" {
+            return Err(Error::WrongRootElementError { found: root.tag_name().name().into() });
         }
+        result.parse_node(root)?;
+        Ok(result)
+    }
+
+    pub fn parse_json(read: impl Read) -> Result<Self> {
+        let json_str = read_text(read)?;
+        Self::parse_json_str(&json_str)
+    }
+
+    pub fn parse_json_str(json_str: &str) -> Result<Self> {
+        let mut result = Tileset::default();
+        let tileset_value: Value = serde_json::from_str(strip_bom(json_str))?;
+        result.parse_json_object(json_object(&tileset_value)?)?;
         Ok(result)
     }
 
@@ -109,25 +218,131 @@ impl Tileset {
         if let Some(image) = image {
             self.image = Some(image);
             for id in 0..self.tile_count {
-                self.tiles.insert(id, Tile::default());
+                self.tiles.insert(id, Tile::with_id(id));
             }
         }
+        self.infer_columns();
 
         // Process children
         for child in tileset_node.children() {
             match child.tag_name().name() {
-                "properties" => self.properties = Properties::parse(child)?,
+                "properties" => self.properties.merge(Properties::parse(child)?),
                 "tileoffset" => self.tile_offset = TileOffset::parse(child)?,
                 "grid" => self.grid = Some(Grid::parse(child)?),
                 "tile" => {
                     let (id, data) = Tile::parse(child)?;
                     self.tiles.insert(id, data);
                 },
+                "wangsets" => {
+                    for wang_set_node in child.children().filter(|node| node.has_tag_name("wangset")) {
+                        self.wang_sets.push(WangSet::parse(wang_set_node)?);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    pub(crate) fn parse_json_object(&mut self, tileset_object: &JsonObject) -> Result<()> {
+
+        // Attributes.
+        for (name, value) in tileset_object {
+            match name.as_str() {
+                "name" => self.name = json_str(value)?.into(),
+                "class" => self.class = json_str(value)?.into(),
+                "tilewidth" => self.tile_width = json_u32(value)?,
+                "tileheight" => self.tile_height = json_u32(value)?,
+                "spacing" => self.spacing = json_u32(value)?,
+                "margin" => self.margin = json_u32(value)?,
+                "tilecount" => self.tile_count = json_u32(value)?,
+                "columns" => self.columns = json_u32(value)?,
+                "objectalignment" => self.object_alignment = ObjectAlignment::parse(json_str(value)?)?,
+                "tilerendersize" => self.tile_render_size = TileRenderSize::parse(json_str(value)?)?,
+                "fillmode" => self.fill_mode = FillMode::parse(json_str(value)?)?,
+                _ => {}
+            }
+        }
+
+        // Same as XML, single image tilesets have every tile populated.
+        if let Some(image) = Image::parse_json(tileset_object)? {
+            self.image = Some(image);
+            for id in 0..self.tile_count {
+                self.tiles.insert(id, Tile::with_id(id));
+            }
+        }
+        self.infer_columns();
+
+        // Nested values.
+        for (name, value) in tileset_object {
+            match name.as_str() {
+                "properties" => self.properties = Properties::parse_json(value)?,
+                "tileoffset" => self.tile_offset = TileOffset::parse_json(value)?,
+                "grid" => self.grid = Some(Grid::parse_json(value)?),
+                "tiles" => {
+                    for tile_value in json_array(value)? {
+                        let (id, data) = Tile::parse_json(tile_value)?;
+                        self.tiles.insert(id, data);
+                    }
+                },
+                "wangsets" => {
+                    for wang_set_value in json_array(value)? {
+                        self.wang_sets.push(WangSet::parse_json(wang_set_value)?);
+                    }
+                },
                 _ => {}
             }
         }
         Ok(())
     }
+
+    /// Writes the tileset's fields into a JSON object, as read by [`parse_json_object`](Self::parse_json_object).
+    /// Tiles without any data besides their id are omitted, as Tiled does.
+    pub(crate) fn write_json(&self, object: &mut JsonObject) {
+        object.insert("name".into(), self.name.as_str().into());
+        if !self.class.is_empty() {
+            object.insert("class".into(), self.class.as_str().into());
+        }
+        object.insert("tilewidth".into(), self.tile_width.into());
+        object.insert("tileheight".into(), self.tile_height.into());
+        object.insert("spacing".into(), self.spacing.into());
+        object.insert("margin".into(), self.margin.into());
+        object.insert("tilecount".into(), self.tile_count.into());
+        object.insert("columns".into(), self.columns.into());
+        object.insert("objectalignment".into(), self.object_alignment.as_str().into());
+        object.insert("tilerendersize".into(), self.tile_render_size.as_str().into());
+        object.insert("fillmode".into(), self.fill_mode.as_str().into());
+        if let Some(image) = &self.image {
+            image.write_json(object);
+        }
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        if self.tile_offset != TileOffset::default() {
+            object.insert("tileoffset".into(), self.tile_offset.to_json());
+        }
+        if let Some(grid) = self.grid {
+            object.insert("grid".into(), grid.to_json());
+        }
+        let mut tiles: Vec<&Tile> = self.tiles.values().filter(|tile| !tile.is_plain()).collect();
+        if !tiles.is_empty() {
+            tiles.sort_by_key(|tile| tile.id());
+            object.insert("tiles".into(), tiles.into_iter().map(Tile::to_json).collect());
+        }
+        if !self.wang_sets.is_empty() {
+            object.insert("wangsets".into(), self.wang_sets.iter().map(WangSet::to_json).collect());
+        }
+    }
+
+    /// Computes columns from the image width when a single image tileset omits them, as Tiled does.
+    fn infer_columns(&mut self) {
+        if self.columns != 0 || self.tile_width == 0 {
+            return;
+        }
+        let Some(image_width) = self.image.as_ref().and_then(|image| image.width()) else { return };
+        let usable_width = (image_width + self.spacing).saturating_sub(2 * self.margin);
+        self.columns = usable_width / (self.tile_width + self.spacing);
+    }
 }
 
 fn parse_image(tileset_node: Node) -> Result<Option<Image>> {
@@ -161,6 +376,20 @@ pub enum FillMode {
 
 impl FillMode {
     pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Stretch => "stretch",
+            Self::PreserveAspectFit => "preserve-aspect-fit",
+        }
+    }
+}
+
+impl FromStr for FillMode {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
         match value {
             "stretch" => Ok(Self::Stretch),
             "preserve-aspect-fit" => Ok(Self::PreserveAspectFit),
@@ -190,6 +419,23 @@ impl Grid {
         }
         Ok(result)
     }
+
+    pub(crate) fn parse_json(value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(value)? {
+            match name.as_str() {
+                "orientation" => result.orientation = Orientation::parse(json_str(value)?)?,
+                "width" => result.width = json_u32(value)?,
+                "height" => result.height = json_u32(value)?,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn to_json(self) -> Value {
+        json!({ "orientation": self.orientation.as_str(), "width": self.width, "height": self.height })
+    }
 }
 
 /// Alignment for tile objects.
@@ -210,6 +456,28 @@ pub enum ObjectAlignment {
 
 impl ObjectAlignment {
     pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Unspecified => "unspecified",
+            Self::TopLeft => "topleft",
+            Self::Top => "top",
+            Self::TopRight => "topright",
+            Self::Left => "left",
+            Self::Center => "center",
+            Self::Right => "right",
+            Self::BottomLeft => "bottomleft",
+            Self::Bottom => "bottom",
+            Self::BottomRight => "bottomright",
+        }
+    }
+}
+
+impl FromStr for ObjectAlignment {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
         match value {
             "unspecified" => Ok(Self::Unspecified),
             "topleft" => Ok(Self::TopLeft),
@@ -237,6 +505,20 @@ pub enum TileRenderSize {
 
 impl TileRenderSize {
     pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Tile => "tile",
+            Self::Grid => "grid",
+        }
+    }
+}
+
+impl FromStr for TileRenderSize {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
         match value {
             "tile" => Ok(Self::Tile),
             "grid" => Ok(Self::Grid),
@@ -247,40 +529,161 @@ impl TileRenderSize {
 
 #[cfg(test)]
 mod test {
-    use crate::Tileset;
+    use crate::{Error, Frame, Gid, Tileset};
 
     #[test]
     fn test_tileset() {
         let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
         let tileset = Tileset::parse_str(xml).unwrap();
         assert!(tileset.image.is_some());
+        assert!(!tileset.is_collection());
+        assert!(tileset.tiles().all(|(_, tile)| !tile.has_own_image()));
+        assert_eq!("grass", tileset.wang_sets()[0].name());
         println!("{tileset:#?}");
 
         // ------- Tests fetching tiles by id -------
         let steve_tile = tileset.tile(0).unwrap();
         let is_steve = steve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(true, is_steve);
+        assert!(is_steve);
 
         let notsteve_tile = tileset.tile(1).unwrap();
         let is_steve = notsteve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(false, is_steve);
+        assert!(!is_steve);
 
         let jerry_tile = tileset.tile(22).unwrap();
         let is_jerry = jerry_tile.properties().get("is_jerry").unwrap().as_bool().unwrap();
-        assert_eq!(true, is_jerry);
+        assert!(is_jerry);
+
+        // ------- Tests tile ids -------
+        assert_eq!(22, tileset.tile(22).unwrap().id());
+        assert_eq!(23, tileset.tile(23).unwrap().id());
+        assert!(tileset.tiles().all(|(id, tile)| id == tile.id()));
 
         // ------- Tests fetching tiles by coordinates -------
         let steve_tile = tileset.tile_at(0, 0).unwrap();
         let is_steve = steve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(true, is_steve);
+        assert!(is_steve);
 
         let notsteve_tile = tileset.tile_at(1, 0).unwrap();
         let is_steve = notsteve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(false, is_steve);
+        assert!(!is_steve);
 
         let jerry_tile = tileset.tile_at(6, 1).unwrap();
         let is_jerry = jerry_tile.properties().get("is_jerry").unwrap().as_bool().unwrap();
-        assert_eq!(true, is_jerry);
+        assert!(is_jerry);
+    }
+
+    #[test]
+    fn test_wrong_root_element() {
+        let xml = include_str!("test_data/finite.tmx");
+        let result = Tileset::parse_str(xml);
+        assert!(matches!(result, Err(Error::WrongRootElementError { found }) if found == "map"));
+    }
+
+    #[test]
+    fn test_tile_or_default() {
+        let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!(144, tileset.tile_or_default(144).id());
+        assert!(tileset.tile_or_default(144).animation().is_some());
+        let missing = tileset.tile_or_default(tileset.tile_count());
+        assert_eq!(tileset.tile_count(), missing.id());
+        assert!(missing.properties().is_empty());
+        assert!(missing.animation().is_none());
+    }
+
+    #[test]
+    fn test_image_dimensions() {
+        let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!(Some((320, 200)), tileset.image_dimensions());
+
+        let xml = r#"
+            <tileset name="spaced" tilewidth="16" tileheight="16" spacing="2" margin="1" tilecount="10" columns="4">
+                <image source="spaced.png"/>
+            </tileset>
+        "#;
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!(Some((2 + 4 * 16 + 3 * 2, 2 + 3 * 16 + 2 * 2)), tileset.image_dimensions());
+
+        let xml = include_str!("test_data/tilesets/collection.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!(None, tileset.image_dimensions());
+    }
+
+    #[test]
+    fn test_effective_size() {
+        let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!((20, 20), tileset.tile(0).unwrap().effective_size(&tileset));
+
+        let xml = include_str!("test_data/tilesets/collection.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!((3, 4), tileset.tile(0).unwrap().effective_size(&tileset));
+        assert_eq!((16, 16), tileset.tile(1).unwrap().effective_size(&tileset));
+        assert_eq!((32, 32), tileset.tile(2).unwrap().effective_size(&tileset));
+    }
+
+    #[test]
+    fn test_load() {
+        let tsx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/tilesets/vikings_of_midgard.tsx");
+        let tsj_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/tilesets/vikings_of_midgard.tsj");
+        let tsx = Tileset::load(tsx_path).unwrap();
+        let tsj = Tileset::load(tsj_path).unwrap();
+        assert_eq!(tsx.name(), tsj.name());
+        assert_eq!(tsx.columns(), tsj.columns());
+        assert_eq!(tsx.image(), tsj.image());
+        assert_eq!(tsx.properties().get("best_color"), tsj.properties().get("best_color"));
+        assert_eq!(tsx.tiles().count(), tsj.tiles().count());
+        assert_eq!(tsx.tile(22).unwrap().properties().get("is_jerry"), tsj.tile(22).unwrap().properties().get("is_jerry"));
+        assert_eq!(tsx.tile(144).unwrap().animation(), tsj.tile(144).unwrap().animation());
+
+        let tsx_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/tilesets/shape.tsx");
+        let tsj_path = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/tilesets/shape.tsj");
+        let tsx = Tileset::load(tsx_path).unwrap();
+        let tsj = Tileset::load(tsj_path).unwrap();
+        let tsx_objects = tsx.tile(72).unwrap().objects().unwrap().objects();
+        let tsj_objects = tsj.tile(72).unwrap().objects().unwrap().objects();
+        assert_eq!(tsx_objects.len(), tsj_objects.len());
+        assert_eq!(tsx_objects[1].x(), tsj_objects[1].x());
+    }
+
+    #[test]
+    fn test_tiles_of_type() {
+        let json = r#"{
+            "name": "hazards",
+            "tilewidth": 16,
+            "tileheight": 16,
+            "tilecount": 4,
+            "columns": 2,
+            "image": "hazards.png",
+            "tiles": [
+                { "id": 0, "type": "damage" },
+                { "id": 2, "class": "damage" },
+                { "id": 3, "class": "heal" }
+            ]
+        }"#;
+        let tileset = Tileset::parse_json_str(json).unwrap();
+        let mut ids: Vec<u32> = tileset.tiles_of_type("damage").map(|(id, _)| id).collect();
+        ids.sort();
+        assert_eq!(vec![0, 2], ids);
+        assert_eq!(1, tileset.tiles_of_type("heal").count());
+        assert_eq!(0, tileset.tiles_of_type("poison").count());
+    }
+
+    #[test]
+    fn test_tile_class() {
+        let xml = r#"
+            <tileset name="units" tilewidth="16" tileheight="16" tilecount="3" columns="3">
+                <image source="units.png" width="48" height="16"/>
+                <tile id="0" class="enemy"/>
+                <tile id="1" type="enemy"/>
+            </tileset>
+        "#;
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!("enemy", tileset.tile(0).unwrap().typ());
+        assert_eq!("enemy", tileset.tile(1).unwrap().typ());
+        assert_eq!("", tileset.tile(2).unwrap().typ());
     }
 
     #[test]
@@ -288,20 +691,22 @@ mod test {
         let xml = include_str!("test_data/tilesets/collection.tsx");
         let tileset = Tileset::parse_str(xml).unwrap();
         assert!(tileset.image.is_none());
+        assert!(tileset.is_collection());
+        assert!(tileset.tiles().all(|(_, tile)| tile.has_own_image()));
 
         // ------- Tests fetching tiles by id -------
         let steve_tile = tileset.tile(0).unwrap();
         let is_steve = steve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(true, is_steve);
+        assert!(is_steve);
 
         let notsteve_tile = tileset.tile(1).unwrap();
         let is_steve = notsteve_tile.properties().get("is_steve").unwrap().as_bool().unwrap();
-        assert_eq!(false, is_steve);
+        assert!(!is_steve);
 
         // ------- Tests fetching tiles by coordinates (always none since it's an image collection) -------
-        assert_eq!(true, tileset.tile_at(0, 0).is_none());
-        assert_eq!(true, tileset.tile_at(1, 0).is_none());
-        assert_eq!(true, tileset.tile_at(2, 2).is_none());
+        assert!(tileset.tile_at(0, 0).is_none());
+        assert!(tileset.tile_at(1, 0).is_none());
+        assert!(tileset.tile_at(2, 2).is_none());
     }
 
     #[test]
@@ -316,6 +721,32 @@ mod test {
         assert!(tile.animation().is_none());
     }
 
+    #[test]
+    fn test_frame_gid() {
+        let frame = Frame { tile_id: 3, duration: 100 };
+        assert_eq!(Some(Gid(53)), frame.gid(50));
+        assert_eq!(None, frame.gid(u32::MAX));
+        assert_eq!(None, frame.gid(Gid::FLIPPED_DIAGONALLY_FLAG));
+
+        let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        let animation = tileset.tile(144).unwrap().animation().unwrap();
+        let first = animation.frames()[0];
+        assert_eq!(Some(Gid(1 + first.tile_id)), first.gid(1));
+    }
+
+    #[test]
+    fn test_animated_tiles() {
+        let xml = include_str!("test_data/tilesets/vikings_of_midgard.tsx");
+        let tileset = Tileset::parse_str(xml).unwrap();
+        let animated: Vec<u32> = tileset.animated_tiles().map(|(id, _)| id).collect();
+        assert!(animated.contains(&144));
+        assert!(!animated.contains(&145));
+        for (id, animation) in tileset.animated_tiles() {
+            assert_eq!(Some(animation), tileset.tile(id).unwrap().animation());
+        }
+    }
+
     #[test]
     fn test_tileset_objects() {
         let xml = include_str!("test_data/tilesets/shape.tsx");
@@ -325,4 +756,38 @@ mod test {
         assert_eq!(2, objects.objects().len());
         assert_eq!(8.37916, objects.objects()[1].x());
     }
+
+    #[test]
+    fn test_tile_image() {
+        let tileset = Tileset::parse_str(include_str!("test_data/tilesets/vikings_of_midgard.tsx")).unwrap();
+        let image = tileset.image().unwrap();
+        assert_eq!(Some(image), tileset.tile_image(0));
+        assert_eq!(Some(image), tileset.tile_image(tileset.tile_count() - 1));
+        assert_eq!(None, tileset.tile_image(tileset.tile_count()));
+
+        let tileset = Tileset::parse_str(include_str!("test_data/tilesets/collection.tsx")).unwrap();
+        assert_eq!("tile_16x16.png", tileset.tile_image(1).unwrap().source());
+        assert_eq!("tile_32x32.png", tileset.tile_image(2).unwrap().source());
+        assert_eq!(None, tileset.tile_image(4));
+    }
+
+    #[test]
+    fn test_infer_columns() {
+        let xml = r#"
+            <tileset name="inferred" tilewidth="32" tileheight="32" tilecount="16">
+                <image source="tiles.png" width="256" height="64"/>
+            </tileset>
+        "#;
+        let tileset = Tileset::parse_str(xml).unwrap();
+        assert_eq!(8, tileset.columns());
+        assert_eq!(9, tileset.tile_at(1, 1).unwrap().id());
+
+        let xml = xml.replace(r#"tilecount="16""#, r#"tilecount="16" margin="2" spacing="1""#).replace("256", "267");
+        let tileset = Tileset::parse_str(&xml).unwrap();
+        assert_eq!(8, tileset.columns());
+
+        let json = r#"{ "name": "inferred", "tilewidth": 32, "tileheight": 32, "tilecount": 16, "image": "tiles.png", "imagewidth": 256, "imageheight": 64 }"#;
+        let tileset = Tileset::parse_json_str(json).unwrap();
+        assert_eq!(8, tileset.columns());
+    }
 }
\ No newline at end of file
@@ -1,9 +1,184 @@
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use serde_json::Value;
 use crate::{Error, Result};
 
-pub fn parse_bool(value: &str) -> Result<bool> {
+/// Parses a boolean attribute.
+/// Tiled writes "0" and "1", but "false" and "true" are accepted as well.
+pub(crate) fn parse_bool(value: &str) -> Result<bool> {
     match value {
-        "0" => Ok(false),
-        "1" => Ok(true),
-        _ => return Err(Error::InvalidLayerError),
+        "0" | "false" => Ok(false),
+        "1" | "true" => Ok(true),
+        _ => Err(Error::ParsingError),
     }
-}
\ No newline at end of file
+}
+
+/// Parses a coordinate, reporting comma decimal separators like "1,5" separately from other malformed numbers.
+pub(crate) fn parse_coordinate(value: &str) -> Result<f32> {
+    value.parse().map_err(|error| {
+        match value.matches(',').count() == 1 && value.replace(',', ".").parse::<f32>().is_ok() {
+            true => Error::CommaDecimalError(value.into()),
+            false => Error::ParseFloatError(error),
+        }
+    })
+}
+
+/// Removes a leading UTF-8 byte order mark, which some editors on Windows emit.
+pub(crate) fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{feff}').unwrap_or(text)
+}
+
+/// Reads text, transcoding it from UTF-16 if it starts with a UTF-16 byte order mark, which some legacy Windows tools emit.
+/// Otherwise, the text must be UTF-8.
+/// Gzipped text is decompressed first when the `flate2` feature is enabled.
+pub(crate) fn read_text(mut read: impl Read) -> Result<String> {
+    let mut bytes = Vec::new();
+    read.read_to_end(&mut bytes)?;
+    #[cfg(feature = "flate2")]
+    if bytes.starts_with(&[0x1F, 0x8B]) {
+        let mut decompressed = Vec::new();
+        flate2::read::GzDecoder::new(bytes.as_slice()).read_to_end(&mut decompressed)?;
+        bytes = decompressed;
+    }
+    let from_bytes: fn([u8; 2]) -> u16 = match bytes.get(..2) {
+        Some([0xFF, 0xFE]) => u16::from_le_bytes,
+        Some([0xFE, 0xFF]) => u16::from_be_bytes,
+        _ => return String::from_utf8(bytes).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into()),
+    };
+    if bytes.len() % 2 != 0 {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "odd number of bytes in UTF-16 text").into());
+    }
+    let units = bytes[2..].chunks_exact(2).map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .collect::<std::result::Result<String, _>>()
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err).into())
+}
+
+/// Extension that determines how a file is parsed, looking past a trailing `.gz` when the `flate2` feature is enabled.
+pub(crate) fn format_extension(path: &Path) -> Option<&str> {
+    let extension = path.extension()?.to_str()?;
+    #[cfg(feature = "flate2")]
+    if extension == "gz" {
+        return Path::new(path.file_stem()?).extension()?.to_str();
+    }
+    Some(extension)
+}
+
+/// Finds the end of the root element of an XML document that may be followed by unrelated data.
+/// Comments, CDATA sections, processing instructions, the doctype and quoted attribute values are skipped, so tags inside them are ignored.
+/// Returns the index just past the root's closing tag, or past the root itself if it is self-closing.
+pub(crate) fn xml_root_end(bytes: &[u8]) -> Option<usize> {
+    let skip_past = |from: usize, pattern: &[u8]| {
+        bytes[from..]
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .map(|position| from + position + pattern.len())
+    };
+    let mut depth = 0_usize;
+    let mut i = 0;
+    while i < bytes.len() {
+        let rest = &bytes[i..];
+        if rest[0] != b'<' {
+            i += 1;
+        }
+        else if rest.starts_with(b"<!--") {
+            i = skip_past(i + 4, b"-->")?;
+        }
+        else if rest.starts_with(b"<![CDATA[") {
+            i = skip_past(i + 9, b"]]>")?;
+        }
+        else if rest.starts_with(b"<?") {
+            i = skip_past(i + 2, b"?>")?;
+        }
+        else {
+            // An element tag, or a doctype whose internal subset may contain '>'.
+            let mut quote = None;
+            let mut subset = false;
+            let mut end = i + 1;
+            loop {
+                let byte = *bytes.get(end)?;
+                match (quote, byte) {
+                    (Some(open), _) if byte == open => quote = None,
+                    (Some(_), _) => {},
+                    (None, b'"' | b'\'') => quote = Some(byte),
+                    (None, b'[') => subset = true,
+                    (None, b']') => subset = false,
+                    (None, b'>') if !subset => break,
+                    _ => {},
+                }
+                end += 1;
+            }
+            match rest[1] {
+                b'!' => {},
+                b'/' => depth = depth.checked_sub(1)?,
+                _ if bytes[end - 1] != b'/' => depth += 1,
+                _ => {},
+            }
+            i = end + 1;
+            if depth == 0 && rest[1] != b'!' {
+                return Some(i);
+            }
+        }
+    }
+    None
+}
+
+/// Joins a path written in a Tiled file against the directory it is relative to.
+/// Absolute paths are returned as-is.
+/// Paths starting with a separator, a drive letter like `C:\` or a UNC prefix like `\\server` count as absolute on any OS.
+/// Both `/` and `\` are treated as separators, so paths saved on any OS can be opened.
+pub(crate) fn resolve_path(base_dir: &Path, relative: &str) -> PathBuf {
+    if is_absolute_path(relative) {
+        return PathBuf::from(relative);
+    }
+    let mut path = base_dir.to_path_buf();
+    for part in relative.split(['/', '\\']).filter(|part| !part.is_empty()) {
+        path.push(part);
+    }
+    path
+}
+
+fn is_absolute_path(path: &str) -> bool {
+    let has_drive_letter = match path.as_bytes() {
+        [drive, b':', b'/' | b'\\', ..] => drive.is_ascii_alphabetic(),
+        _ => false,
+    };
+    has_drive_letter || path.starts_with(['/', '\\'])
+}
+
+/// Key/value pairs of a JSON object.
+pub(crate) type JsonObject = serde_json::Map<String, Value>;
+
+pub(crate) fn json_object(value: &Value) -> Result<&JsonObject> {
+    value.as_object().ok_or(Error::JsonParsingError)
+}
+
+pub(crate) fn json_array(value: &Value) -> Result<&[Value]> {
+    value.as_array().map(|array| array.as_slice()).ok_or(Error::JsonParsingError)
+}
+
+pub(crate) fn json_str(value: &Value) -> Result<&str> {
+    value.as_str().ok_or(Error::JsonParsingError)
+}
+
+pub(crate) fn json_bool(value: &Value) -> Result<bool> {
+    value.as_bool().ok_or(Error::JsonParsingError)
+}
+
+pub(crate) fn json_u32(value: &Value) -> Result<u32> {
+    let value = value.as_u64().ok_or(Error::JsonParsingError)?;
+    u32::try_from(value).map_err(|_| Error::JsonParsingError)
+}
+
+pub(crate) fn json_i32(value: &Value) -> Result<i32> {
+    let value = value.as_i64().ok_or(Error::JsonParsingError)?;
+    i32::try_from(value).map_err(|_| Error::JsonParsingError)
+}
+
+pub(crate) fn json_i64(value: &Value) -> Result<i64> {
+    value.as_i64().ok_or(Error::JsonParsingError)
+}
+
+pub(crate) fn json_f32(value: &Value) -> Result<f32> {
+    value.as_f64().map(|value| value as f32).ok_or(Error::JsonParsingError)
+}
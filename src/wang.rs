@@ -0,0 +1,418 @@
+use std::str::FromStr;
+use roxmltree::Node;
+use serde_json::{json, Value};
+use crate::{json_array, json_f32, json_i32, json_object, json_str, json_u32, Color, Error, JsonObject, Properties, Result};
+
+
+/// A set of terrain colors used for automatically choosing tiles in a [`Tileset`](crate::Tileset).
+#[derive(Clone, Default, Debug)]
+pub struct WangSet {
+    name: String,
+    class: String,
+    typ: WangSetType,
+    tile: Option<u32>,
+    properties: Properties,
+    colors: Vec<WangColor>,
+    wang_tiles: Vec<WangTile>,
+}
+
+impl WangSet {
+    pub fn name(&self) -> &str { &self.name }
+    pub fn class(&self) -> &str { &self.class }
+    pub fn typ(&self) -> WangSetType { self.typ }
+
+    /// Local id of the tile representing the set.
+    /// None if the set has no such tile.
+    pub fn tile(&self) -> Option<u32> { self.tile }
+
+    pub fn properties(&self) -> &Properties { &self.properties }
+
+    /// Colors of the set.
+    /// Color indices in [`WangTile::wang_id`] start at 1, so color `i` is at index `i - 1`.
+    pub fn colors(&self) -> &[WangColor] { &self.colors }
+
+    pub fn wang_tiles(&self) -> &[WangTile] { &self.wang_tiles }
+
+    /// Local id of the first tile whose wang id matches a pattern.
+    /// Entries of 0 in the pattern match any color.
+    pub fn tile_for_pattern(&self, wang_id: [u8; 8]) -> Option<u32> {
+        self.wang_tiles
+            .iter()
+            .find(|wang_tile| wang_id
+                .iter()
+                .zip(wang_tile.wang_id)
+                .all(|(&expected, actual)| expected == 0 || expected == actual)
+            )
+            .map(|wang_tile| wang_tile.tile_id)
+    }
+
+    pub(crate) fn parse(wang_set_node: Node) -> Result<Self> {
+        let mut result = Self::default();
+        for attr in wang_set_node.attributes() {
+            match attr.name() {
+                "name" => result.name = attr.value().into(),
+                "class" => result.class = attr.value().into(),
+                "type" => result.typ = WangSetType::parse(attr.value())?,
+                "tile" => result.tile = parse_tile(attr.value().parse()?),
+                _ => {}
+            }
+        }
+        for child in wang_set_node.children() {
+            match child.tag_name().name() {
+                "properties" => result.properties.merge(Properties::parse(child)?),
+                "wangcolor" => result.colors.push(WangColor::parse(child)?),
+                "wangtile" => result.wang_tiles.push(WangTile::parse(child)?),
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn parse_json(wang_set_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(wang_set_value)? {
+            match name.as_str() {
+                "name" => result.name = json_str(value)?.into(),
+                "class" => result.class = json_str(value)?.into(),
+                "type" => result.typ = WangSetType::parse(json_str(value)?)?,
+                "tile" => result.tile = parse_tile(json_i32(value)?),
+                "properties" => result.properties = Properties::parse_json(value)?,
+                "colors" => {
+                    for color_value in json_array(value)? {
+                        result.colors.push(WangColor::parse_json(color_value)?);
+                    }
+                },
+                "wangtiles" => {
+                    for wang_tile_value in json_array(value)? {
+                        result.wang_tiles.push(WangTile::parse_json(wang_tile_value)?);
+                    }
+                },
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    pub(crate) fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("name".into(), self.name.as_str().into());
+        object.insert("class".into(), self.class.as_str().into());
+        object.insert("type".into(), self.typ.as_str().into());
+        object.insert("tile".into(), tile_to_json(self.tile));
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        object.insert("colors".into(), self.colors.iter().map(WangColor::to_json).collect());
+        object.insert("wangtiles".into(), self.wang_tiles.iter().map(|wang_tile| wang_tile.to_json()).collect());
+        Value::Object(object)
+    }
+}
+
+/// Whether a [`WangSet`] matches tiles by their corners, edges, or both.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum WangSetType {
+    #[default]
+    Corner,
+    Edge,
+    Mixed,
+}
+
+impl WangSetType {
+    pub fn parse(value: &str) -> Result<Self> {
+        value.parse()
+    }
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::Corner => "corner",
+            Self::Edge => "edge",
+            Self::Mixed => "mixed",
+        }
+    }
+}
+
+impl FromStr for WangSetType {
+    type Err = Error;
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "corner" => Ok(Self::Corner),
+            "edge" => Ok(Self::Edge),
+            "mixed" => Ok(Self::Mixed),
+            _ => Err(Error::ParsingError),
+        }
+    }
+}
+
+/// A terrain color in a [`WangSet`].
+#[derive(Clone, Debug)]
+pub struct WangColor {
+    name: String,
+    class: String,
+    color: Color,
+    tile: Option<u32>,
+    probability: f32,
+    properties: Properties,
+}
+
+impl Default for WangColor {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            class: String::new(),
+            color: Color::default(),
+            tile: None,
+            probability: 1.0,
+            properties: Properties::default(),
+        }
+    }
+}
+
+impl WangColor {
+    pub fn name(&self) -> &str { &self.name }
+    pub fn class(&self) -> &str { &self.class }
+    pub fn color(&self) -> Color { self.color }
+
+    /// Local id of the tile representing the color.
+    /// None if the color has no such tile.
+    pub fn tile(&self) -> Option<u32> { self.tile }
+
+    /// Relative likelihood of the color being picked when several tiles match.
+    pub fn probability(&self) -> f32 { self.probability }
+
+    pub fn properties(&self) -> &Properties { &self.properties }
+
+    fn parse(wang_color_node: Node) -> Result<Self> {
+        let mut result = Self::default();
+        for attr in wang_color_node.attributes() {
+            match attr.name() {
+                "name" => result.name = attr.value().into(),
+                "class" => result.class = attr.value().into(),
+                "color" => result.color = attr.value().parse()?,
+                "tile" => result.tile = parse_tile(attr.value().parse()?),
+                "probability" => result.probability = attr.value().parse()?,
+                _ => {}
+            }
+        }
+        for child in wang_color_node.children() {
+            if child.tag_name().name() == "properties" {
+                result.properties.merge(Properties::parse(child)?);
+            }
+        }
+        Ok(result)
+    }
+
+    fn parse_json(wang_color_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(wang_color_value)? {
+            match name.as_str() {
+                "name" => result.name = json_str(value)?.into(),
+                "class" => result.class = json_str(value)?.into(),
+                "color" => result.color = json_str(value)?.parse()?,
+                "tile" => result.tile = parse_tile(json_i32(value)?),
+                "probability" => result.probability = json_f32(value)?,
+                "properties" => result.properties = Properties::parse_json(value)?,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn to_json(&self) -> Value {
+        let mut object = JsonObject::new();
+        object.insert("name".into(), self.name.as_str().into());
+        object.insert("class".into(), self.class.as_str().into());
+        object.insert("color".into(), self.color.to_string().into());
+        object.insert("tile".into(), tile_to_json(self.tile));
+        object.insert("probability".into(), self.probability.into());
+        if !self.properties.is_empty() {
+            object.insert("properties".into(), self.properties.to_json());
+        }
+        Value::Object(object)
+    }
+}
+
+/// Colors of a tile's corners and edges in a [`WangSet`].
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub struct WangTile {
+    pub tile_id: u32,
+    /// Color indices, clockwise from the top edge: top, top-right, right, bottom-right, bottom, bottom-left, left, top-left.
+    /// 0 means no color.
+    pub wang_id: [u8; 8],
+}
+
+impl WangTile {
+    fn parse(wang_tile_node: Node) -> Result<Self> {
+        let mut result = Self::default();
+        for attr in wang_tile_node.attributes() {
+            match attr.name() {
+                "tileid" => result.tile_id = attr.value().parse()?,
+                "wangid" => result.wang_id = parse_wang_id(attr.value())?,
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn parse_json(wang_tile_value: &Value) -> Result<Self> {
+        let mut result = Self::default();
+        for (name, value) in json_object(wang_tile_value)? {
+            match name.as_str() {
+                "tileid" => result.tile_id = json_u32(value)?,
+                "wangid" => {
+                    let colors: Vec<u8> = json_array(value)?
+                        .iter()
+                        .map(|color| u8::try_from(json_u32(color)?).map_err(|_| Error::JsonParsingError))
+                        .collect::<Result<_>>()?;
+                    result.wang_id = colors.try_into().map_err(|_| Error::JsonParsingError)?;
+                },
+                _ => {}
+            }
+        }
+        Ok(result)
+    }
+
+    fn to_json(self) -> Value {
+        json!({ "tileid": self.tile_id, "wangid": self.wang_id })
+    }
+}
+
+/// Parses a comma separated wang id.
+/// Tilesets saved before Tiled 1.5 store it as a hex number instead, with one color per nibble starting from the least significant.
+fn parse_wang_id(wang_id: &str) -> Result<[u8; 8]> {
+    if let Some(hex) = wang_id.strip_prefix("0x") {
+        let legacy = u32::from_str_radix(hex, 16)?;
+        return Ok(std::array::from_fn(|i| ((legacy >> (i * 4)) & 0xF) as u8));
+    }
+    let colors: Vec<u8> = wang_id
+        .split(',')
+        .map(|color| color.trim().parse())
+        .collect::<std::result::Result<_, _>>()?;
+    colors.try_into().map_err(|_| Error::ParsingError)
+}
+
+/// Tiled stores a tile id of -1 when no tile is set.
+fn parse_tile(tile: i32) -> Option<u32> {
+    u32::try_from(tile).ok()
+}
+
+fn tile_to_json(tile: Option<u32>) -> Value {
+    match tile {
+        Some(tile) => tile.into(),
+        None => (-1).into(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Color, Error, Tileset, WangSetType};
+
+    const XML: &str = r##"
+        <tileset name="terrain" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+            <image source="terrain.png" width="32" height="32"/>
+            <wangsets>
+                <wangset name="ground" type="corner" tile="-1">
+                    <wangcolor name="grass" color="#00ff00" tile="0" probability="0.5"/>
+                    <wangcolor name="dirt" color="#804000" tile="-1" probability="1"/>
+                    <wangtile tileid="0" wangid="0,1,0,1,0,1,0,1"/>
+                    <wangtile tileid="1" wangid="0,1,0,2,0,2,0,1"/>
+                    <wangtile tileid="2" wangid="0,2,0,2,0,2,0,2"/>
+                </wangset>
+            </wangsets>
+        </tileset>
+    "##;
+
+    #[test]
+    fn test_wang_set() {
+        let tileset = Tileset::parse_str(XML).unwrap();
+        let wang_set = &tileset.wang_sets()[0];
+        assert_eq!("ground", wang_set.name());
+        assert_eq!(WangSetType::Corner, wang_set.typ());
+        assert_eq!(None, wang_set.tile());
+        assert_eq!(3, wang_set.wang_tiles().len());
+
+        let grass = &wang_set.colors()[0];
+        assert_eq!("grass", grass.name());
+        assert_eq!(Color { r: 0, g: 255, b: 0, a: 255 }, grass.color());
+        assert_eq!(Some(0), grass.tile());
+        assert_eq!(0.5, grass.probability());
+        assert_eq!(None, wang_set.colors()[1].tile());
+    }
+
+    #[test]
+    fn test_legacy_wang_id() {
+        let xml = r##"
+            <tileset name="terrain" tilewidth="16" tileheight="16" tilecount="4" columns="2">
+                <image source="terrain.png" width="32" height="32"/>
+                <wangsets>
+                    <wangset name="ground" tile="-1">
+                        <wangcolor name="grass" color="#00ff00" tile="0" probability="1"/>
+                        <wangcolor name="dirt" color="#804000" tile="-1" probability="1"/>
+                        <wangtile tileid="1" wangid="0x10202010"/>
+                    </wangset>
+                </wangsets>
+            </tileset>
+        "##;
+        let tileset = Tileset::parse_str(xml).unwrap();
+        let wang_tile = tileset.wang_sets()[0].wang_tiles()[0];
+        assert_eq!(1, wang_tile.tile_id);
+        assert_eq!([0, 1, 0, 2, 0, 2, 0, 1], wang_tile.wang_id);
+    }
+
+    #[test]
+    fn test_tile_for_pattern() {
+        let tileset = Tileset::parse_str(XML).unwrap();
+        let wang_set = &tileset.wang_sets()[0];
+        assert_eq!(Some(0), wang_set.tile_for_pattern([0, 1, 0, 1, 0, 1, 0, 1]));
+        assert_eq!(Some(1), wang_set.tile_for_pattern([0, 1, 0, 2, 0, 2, 0, 1]));
+        assert_eq!(Some(1), wang_set.tile_for_pattern([0, 0, 0, 2, 0, 0, 0, 1]));
+        assert_eq!(Some(2), wang_set.tile_for_pattern([0, 2, 0, 0, 0, 0, 0, 0]));
+        assert_eq!(None, wang_set.tile_for_pattern([0, 2, 0, 1, 0, 1, 0, 1]));
+    }
+
+    #[test]
+    fn test_validate_wangsets() {
+        let tileset = Tileset::parse_str(XML).unwrap();
+        assert!(tileset.validate_wangsets().is_ok());
+
+        let xml = XML.replace(r#"<wangtile tileid="2""#, r#"<wangtile tileid="4""#);
+        let tileset = Tileset::parse_str(&xml).unwrap();
+        let result = tileset.validate_wangsets();
+        assert!(matches!(result, Err(Error::InvalidWangTileError { wang_set, tile_id: 4 }) if wang_set == "ground"));
+
+        let xml = XML.replace("0,2,0,2,0,2,0,2", "0,2,0,3,0,2,0,2");
+        let tileset = Tileset::parse_str(&xml).unwrap();
+        let result = tileset.validate_wangsets();
+        assert!(matches!(result, Err(Error::InvalidWangTileError { tile_id: 2, .. })));
+    }
+
+    #[test]
+    fn test_wang_set_json() {
+        let json = r##"{
+            "name": "terrain", "tilewidth": 16, "tileheight": 16, "tilecount": 4, "columns": 2,
+            "image": "terrain.png", "imagewidth": 32, "imageheight": 32,
+            "wangsets": [{
+                "name": "ground", "type": "edge", "tile": 3,
+                "colors": [
+                    { "name": "grass", "color": "#00ff00", "tile": -1, "probability": 0.5 },
+                    { "name": "water", "color": "#800000ff", "tile": 2 }
+                ],
+                "wangtiles": [{ "tileid": 3, "wangid": [1, 0, 1, 0, 1, 0, 1, 0] }]
+            }]
+        }"##;
+        let tileset = Tileset::parse_json_str(json).unwrap();
+        let wang_set = &tileset.wang_sets()[0];
+        assert_eq!(WangSetType::Edge, wang_set.typ());
+        assert_eq!(Some(3), wang_set.tile());
+        assert_eq!(0.5, wang_set.colors()[0].probability());
+        let colors: Vec<(&str, Color, f32)> = wang_set.colors()
+            .iter()
+            .map(|color| (color.name(), color.color(), color.probability()))
+            .collect();
+        let expected = vec![
+            ("grass", Color { r: 0, g: 255, b: 0, a: 255 }, 0.5),
+            ("water", Color { r: 0, g: 0, b: 255, a: 128 }, 1.0),
+        ];
+        assert_eq!(expected, colors);
+        assert_eq!(Some(3), wang_set.tile_for_pattern([1, 0, 1, 0, 1, 0, 1, 0]));
+    }
+}
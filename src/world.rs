@@ -1,10 +1,14 @@
-use std::io::Read;
-use serde::Deserialize;
-use crate::Result;
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+use crate::{strip_bom, Gid, Map, Result, Tile};
 
 #[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct World {
     pub maps: Vec<MapRef>,
+    #[serde(rename = "onlyShowAdjacentMaps", default)]
+    pub only_show_adjacent_maps: bool,
 }
 
 impl World {
@@ -16,13 +20,94 @@ impl World {
     }
 
     pub fn parse_str(json_str: &str) -> Result<Self> {
-        let world = serde_json::de::from_str(json_str)?;
+        let world = serde_json::de::from_str(strip_bom(json_str))?;
         Ok(world)
     }
+
+    /// Finds a world file in a directory, and parses it.
+    /// If the directory holds several, the first by file name is used.
+    /// None if the directory holds no file ending in `.world`.
+    pub fn find_in(dir: impl AsRef<Path>) -> Result<Option<(PathBuf, Self)>> {
+        let mut paths = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.is_file() && path.extension().is_some_and(|ext| ext == "world") {
+                paths.push(path);
+            }
+        }
+        let Some(path) = paths.into_iter().min() else { return Ok(None) };
+        let world = Self::parse(File::open(&path)?)?;
+        Ok(Some((path, world)))
+    }
+
+    /// Writes the world as a Tiled world file.
+    pub fn write_json(&self, write: impl Write) -> Result<()> {
+        let world_json = WorldJson {
+            maps: &self.maps,
+            only_show_adjacent_maps: self.only_show_adjacent_maps,
+            typ: "world",
+        };
+        serde_json::to_writer_pretty(write, &world_json)?;
+        Ok(())
+    }
+
+    /// Loads every map in the world, so that tiles can be looked up in world coordinates.
+    /// Map file names are relative to `dir`, which is usually the directory of the world file.
+    pub fn load_stitched(&self, dir: impl AsRef<Path>) -> Result<StitchedWorld> {
+        let dir = dir.as_ref();
+        let mut maps = Vec::with_capacity(self.maps.len());
+        for map_ref in &self.maps {
+            let map = Map::load(dir.join(&map_ref.file_name))?;
+            maps.push((map_ref.clone(), map));
+        }
+        Ok(StitchedWorld { maps })
+    }
 }
 
 
-#[derive(Deserialize, Clone, Eq, PartialEq, Debug)]
+/// Layout of a world file, as written by [`World::write_json`].
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct WorldJson<'a> {
+    maps: &'a [MapRef],
+    only_show_adjacent_maps: bool,
+    #[serde(rename = "type")]
+    typ: &'static str,
+}
+
+/// The maps of a [`World`], loaded and placed in a single coordinate space.
+#[derive(Debug)]
+pub struct StitchedWorld {
+    maps: Vec<(MapRef, Map)>,
+}
+
+impl StitchedWorld {
+
+    /// Iterates over maps along with their placement in the world.
+    pub fn maps(&self) -> impl Iterator<Item = (&MapRef, &Map)> {
+        self.maps.iter().map(|(map_ref, map)| (map_ref, map))
+    }
+
+    /// Finds the map containing a point, in pixels.
+    pub fn map_at(&self, world_x: i32, world_y: i32) -> Option<(&MapRef, &Map)> {
+        self.maps().find(|(map_ref, _)| {
+            world_x >= map_ref.x && world_x < map_ref.x + map_ref.width as i32 &&
+            world_y >= map_ref.y && world_y < map_ref.y + map_ref.height as i32
+        })
+    }
+
+    /// Gets the topmost non-null tile at a point, in pixels, across all tile layers of the map containing it.
+    /// None if no map contains the point, or every tile layer is empty there.
+    pub fn tile_at(&self, world_x: i32, world_y: i32) -> Option<(Gid, &Tile)> {
+        let (map_ref, map) = self.map_at(world_x, world_y)?;
+        let x = (world_x - map_ref.x).div_euclid(map.tile_width() as i32);
+        let y = (world_y - map_ref.y).div_euclid(map.tile_height() as i32);
+        let gid = map.first_gid_at(x, y)?;
+        Some((gid, map.tile_for_gid(gid)?))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug)]
 pub struct MapRef {
     #[serde(rename = "fileName")]
     pub file_name: String,
@@ -34,7 +119,7 @@ pub struct MapRef {
 
 #[cfg(test)]
 mod test {
-    use crate::{World, MapRef};
+    use crate::{Gid, World, MapRef};
 
     #[test]
     fn test_deserialize() {
@@ -76,7 +161,90 @@ mod test {
                     height: 384,
                 }
             ],
+            only_show_adjacent_maps: false,
         };
         assert_eq!(expected, actual);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_bom() {
+        let json = "\u{feff}{ \"maps\": [] }";
+        let world = World::parse_str(json).unwrap();
+        assert!(world.maps.is_empty());
+        assert!(!world.only_show_adjacent_maps);
+    }
+
+    #[test]
+    fn test_only_show_adjacent_maps() {
+        let json = r#"{ "maps": [], "onlyShowAdjacentMaps": true, "type": "world" }"#;
+        let world = World::parse_str(json).unwrap();
+        assert!(world.only_show_adjacent_maps);
+
+        let mut written = Vec::new();
+        world.write_json(&mut written).unwrap();
+        assert_eq!(world, World::parse(written.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn test_write_json() {
+        let json = include_str!("test_data/world/stitched.world");
+        let world = World::parse_str(json).unwrap();
+        let mut written = Vec::new();
+        world.write_json(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert_eq!(world, World::parse_str(&written).unwrap());
+
+        let value: serde_json::Value = serde_json::from_str(&written).unwrap();
+        assert_eq!("world", value["type"]);
+        assert_eq!(false, value["onlyShowAdjacentMaps"]);
+        assert_eq!("map_2.tmx", value["maps"][1]["fileName"]);
+    }
+
+    #[test]
+    fn test_load_stitched() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/src/test_data/world");
+        let json = include_str!("test_data/world/stitched.world");
+        let world = World::parse_str(json).unwrap().load_stitched(dir).unwrap();
+        assert_eq!(2, world.maps().count());
+
+        // Last column of map_1.
+        let (gid, tile) = world.tile_at(59, 39).unwrap();
+        assert_eq!(Gid(6), gid);
+        assert_eq!(5, tile.id());
+        assert_eq!("map_1.tmx", world.map_at(59, 39).unwrap().0.file_name);
+
+        // First column of map_2.
+        let (gid, tile) = world.tile_at(60, 0).unwrap();
+        assert_eq!(Gid(11), gid);
+        assert_eq!(10, tile.id());
+        assert_eq!("map_2.tmx", world.map_at(60, 0).unwrap().0.file_name);
+
+        // Upper layers take precedence.
+        let (gid, tile) = world.tile_at(99, 39).unwrap();
+        assert_eq!(21, gid.value());
+        assert!(gid.is_flipped_horizontally());
+        assert_eq!(20, tile.id());
+
+        assert!(world.tile_at(100, 0).is_none());
+        assert!(world.tile_at(-1, 0).is_none());
+    }
+
+    #[test]
+    fn test_find_in() {
+        let dir = std::env::temp_dir().join(format!("tiled_parser_find_world_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("map_1.tmx"), "").unwrap();
+        let none = World::find_in(&dir);
+
+        let world = include_str!("test_data/world/stitched.world");
+        std::fs::write(dir.join("b.world"), world).unwrap();
+        std::fs::write(dir.join("a.world"), world).unwrap();
+        let found = World::find_in(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert!(none.unwrap().is_none());
+        let (path, found) = found.unwrap().unwrap();
+        assert_eq!(dir.join("a.world"), path);
+        assert_eq!(World::parse_str(world).unwrap(), found);
+    }
+}